@@ -0,0 +1,113 @@
+//! Event-driven backtest: historical trades go straight into the `Engine` on
+//! an `EventClock`, so the same input and parameters always give the same
+//! report.
+
+use crate::cli::BacktestArgs;
+use crate::clock::EventClock;
+use crate::engine::{Engine, SymbolState};
+use crate::performance::{self, Stats};
+use crate::{history, position, AggTrade};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+#[derive(Debug, Default, Serialize)]
+pub struct SymbolReport {
+    pub trades: u64,
+    pub fills: u64,
+    pub round_trips: u64,
+    /// Net of fees and slippage
+    pub pnl: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+    pub slippage: f64,
+    pub first_ts: u64,
+    pub last_ts: u64,
+    #[serde(flatten)]
+    pub stats: Stats,
+    /// (interval end ms, equity) every --stats-interval-secs, plus the final mark
+    pub equity_curve: Vec<(u64, f64)>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub files: usize,
+    pub trades: u64,
+    pub skipped: u64,
+    pub fills: u64,
+    pub pnl: f64,
+    pub fees: f64,
+    pub slippage: f64,
+    pub symbols: BTreeMap<String, SymbolReport>,
+}
+
+impl SymbolReport {
+    fn observe(&mut self, tr: &AggTrade) {
+        if self.trades == 0 {
+            self.first_ts = tr.T;
+        }
+        self.trades += 1;
+        self.last_ts = tr.T;
+    }
+
+    /// Copy end-of-run totals from the engine's book.
+    fn finish(&mut self, book: &SymbolState) {
+        self.fills = book.fills;
+        self.round_trips = book.position.round_trips;
+        self.pnl = book.equity();
+        self.realized_pnl = book.position.realized;
+        self.unrealized_pnl = book.position.unrealized(book.last_price);
+        self.fees = book.position.fees;
+        self.slippage = book.slippage;
+        self.stats = book.perf.stats();
+        self.equity_curve = book.perf.curve.clone();
+        self.equity_curve.push((self.last_ts, self.pnl));
+    }
+}
+
+pub fn run(args: &BacktestArgs) -> anyhow::Result<Report> {
+    let files = history::collect_files(&args.files)?;
+    anyhow::ensure!(!files.is_empty(), "no files to backtest");
+
+    let mut engine = Engine::new(args.strategy.clone(), Box::new(EventClock::default()));
+    engine.set_log_fills(!args.quiet);
+    let mut report = Report { files: files.len(), ..Default::default() };
+    history::for_each_merged(&files, args.symbol.as_deref(), |item| {
+        let Some((_, tr)) = item else {
+            report.skipped += 1;
+            return ControlFlow::Continue(());
+        };
+        engine.on_trade(&tr);
+        report.symbols.entry(tr.s.clone()).or_default().observe(&tr);
+        report.trades += 1;
+        ControlFlow::Continue(())
+    })?;
+    for (sym, s) in report.symbols.iter_mut() {
+        s.finish(engine.book(sym).expect("engine creates a book per traded symbol"));
+    }
+    report.fills = report.symbols.values().map(|s| s.fills).sum();
+    report.pnl = report.symbols.values().map(|s| s.pnl).sum();
+    report.fees = report.symbols.values().map(|s| s.fees).sum();
+    report.slippage = report.symbols.values().map(|s| s.slippage).sum();
+    if let Some(path) = &args.strategy.ledger {
+        position::write_ledger(path, engine.ledger())?;
+        println!("Ledger written to {}", path.display());
+    }
+    Ok(report)
+}
+
+pub fn print(report: &Report) {
+    println!("=== Backtest report ===");
+    println!(
+        "files={} trades={} skipped={} fills={} pnl={:.2} fees={:.2} slippage={:.2}",
+        report.files, report.trades, report.skipped, report.fills, report.pnl, report.fees, report.slippage
+    );
+    for (sym, s) in &report.symbols {
+        println!(
+            "{sym}: trades={} fills={} round_trips={} pnl={:.2} (realized={:.2} unrealized={:.2}) fees={:.2} slippage={:.2}",
+            s.trades, s.fills, s.round_trips, s.pnl, s.realized_pnl, s.unrealized_pnl, s.fees, s.slippage
+        );
+        println!("{sym}: {}", performance::format_stats(&s.stats));
+    }
+}
@@ -0,0 +1,178 @@
+//! OHLCV bar aggregation from the trade stream. Bars close on elapsed time,
+//! traded base volume, traded quote notional or trade count. A trade is never
+//! split across bars, so volume/dollar bars overshoot their size slightly.
+
+use crate::strategy::Trade;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BarKind {
+    /// Fixed trade-time buckets, closed on the engine clock; size in seconds
+    Time,
+    /// Close after `size` units of base asset traded
+    Volume,
+    /// Close after `size` of quote notional (price * qty) traded
+    Dollar,
+    /// Close after `size` trades
+    Tick,
+}
+
+/// Strategies read whichever fields they need; the last completed bar of
+/// each symbol is also exported in full as `quant_bar_*` gauges.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Bar {
+    pub open_ms: u64,
+    pub close_ms: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    /// Taker-buy volume (`AggTrade.m` == false)
+    pub buy_volume: f64,
+    /// Taker-sell volume (`AggTrade.m` == true, buyer was the maker)
+    pub sell_volume: f64,
+    pub notional: f64,
+    pub trades: u64,
+}
+
+impl Bar {
+    fn start(t: &Trade, open_ms: u64) -> Self {
+        Self {
+            open_ms,
+            close_ms: t.ts_ms,
+            open: t.price,
+            high: t.price,
+            low: t.price,
+            close: t.price,
+            volume: 0.0,
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+            notional: 0.0,
+            trades: 0,
+        }
+    }
+
+    fn add(&mut self, t: &Trade) {
+        self.close_ms = self.close_ms.max(t.ts_ms);
+        self.high = self.high.max(t.price);
+        self.low = self.low.min(t.price);
+        self.close = t.price;
+        self.volume += t.qty;
+        if t.is_buyer_maker {
+            self.sell_volume += t.qty;
+        } else {
+            self.buy_volume += t.qty;
+        }
+        self.notional += t.price * t.qty;
+        self.trades += 1;
+    }
+}
+
+/// The bar in progress, kept across restarts so a resumed run finishes it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BarState {
+    cur: Option<Bar>,
+    closed_until_ms: u64,
+}
+
+pub struct BarBuilder {
+    kind: BarKind,
+    size: f64,
+    cur: Option<Bar>,
+    // time bars: end of the last closed bucket; later trades stamped before it
+    // (late after a close on the clock) go into the open bar instead
+    closed_until_ms: u64,
+}
+
+impl BarBuilder {
+    pub fn new(kind: BarKind, size: f64) -> Self {
+        Self { kind, size: size.max(f64::MIN_POSITIVE), cur: None, closed_until_ms: 0 }
+    }
+
+    pub fn state(&self) -> BarState {
+        BarState { cur: self.cur, closed_until_ms: self.closed_until_ms }
+    }
+
+    pub fn restore(&mut self, state: BarState) {
+        self.cur = state.cur;
+        self.closed_until_ms = state.closed_until_ms;
+    }
+
+    fn time_bucket_ms(&self) -> u64 {
+        ((self.size * 1000.0) as u64).max(1)
+    }
+
+    /// Add a trade; returns a bar if one completed. For time bars the completed
+    /// bar is the previous bucket and the trade opens the next one.
+    pub fn on_trade(&mut self, t: &Trade) -> Option<Bar> {
+        if self.kind == BarKind::Time {
+            let bucket = self.time_bucket_ms();
+            let open_ms = (t.ts_ms - t.ts_ms % bucket).max(self.closed_until_ms);
+            let done = match self.cur {
+                Some(b) if open_ms > b.open_ms => self.close(),
+                _ => None,
+            };
+            self.cur.get_or_insert_with(|| Bar::start(t, open_ms)).add(t);
+            return done;
+        }
+
+        let bar = self.cur.get_or_insert_with(|| Bar::start(t, t.ts_ms));
+        bar.add(t);
+        let filled = match self.kind {
+            BarKind::Volume => bar.volume >= self.size,
+            BarKind::Dollar => bar.notional >= self.size,
+            BarKind::Tick => bar.trades as f64 >= self.size,
+            BarKind::Time => unreachable!(),
+        };
+        if filled {
+            self.cur.take()
+        } else {
+            None
+        }
+    }
+
+    /// Close a time bar whose bucket has ended even though no trade arrived yet.
+    /// `now_ms` is the engine clock, so outside live this is data time.
+    pub fn on_time(&mut self, now_ms: u64) -> Option<Bar> {
+        if self.kind != BarKind::Time {
+            return None;
+        }
+        let bucket = self.time_bucket_ms();
+        match self.cur {
+            Some(b) if now_ms >= b.open_ms + bucket => self.close(),
+            _ => None,
+        }
+    }
+
+    fn close(&mut self) -> Option<Bar> {
+        let bar = self.cur.take()?;
+        self.closed_until_ms = bar.open_ms + self.time_bucket_ms();
+        Some(bar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(ts_ms: u64, price: f64) -> Trade {
+        Trade { ts_ms, price, qty: 1.0, is_buyer_maker: false }
+    }
+
+    #[test]
+    fn late_trade_after_clock_close_joins_next_bar() {
+        let mut b = BarBuilder::new(BarKind::Time, 60.0);
+        assert!(b.on_trade(&trade(1_000, 1.0)).is_none());
+        let first = b.on_time(60_005).expect("bucket ended on the clock");
+        assert_eq!((first.open_ms, first.trades), (0, 1));
+
+        // stamped in the closed bucket, but arrived after it closed
+        assert!(b.on_trade(&trade(59_990, 2.0)).is_none());
+        assert!(b.on_time(60_010).is_none());
+        assert!(b.on_trade(&trade(61_000, 3.0)).is_none());
+        let second = b.on_time(120_000).expect("second bucket ended");
+        assert_eq!((second.open_ms, second.trades, second.close), (60_000, 2, 3.0));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::bars::BarKind;
+use crate::indicators::MaKind;
+use crate::strategy::StrategyKind;
+use crate::{costs, exits, health, queue, recorder, risk, session, sizing};
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug, Clone)]
+#[command(name = "quant-mini")]
+#[command(about = "Binance WS -> MA strategy -> paper trading -> metrics")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // live paper trading, the default when no subcommand is given
+    #[command(flatten)]
+    pub live: Args,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Capture raw WS frames to disk for later replay, without trading
+    Record(RecordArgs),
+    /// Feed recorded files through the strategy and paper trader
+    Replay(ReplayArgs),
+    /// Run the strategy over historical files on event time and print a report
+    Backtest(BacktestArgs),
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct Args {
+    #[command(flatten)]
+    pub feed: FeedArgs,
+
+    #[command(flatten)]
+    pub strategy: StrategyArgs,
+
+    /// Metrics server port
+    #[arg(long, default_value_t = 9000)]
+    pub metrics_port: u16,
+
+    /// Bearer token for the POST /control/* endpoints (disabled when unset)
+    #[arg(long, env = "QUANT_CONTROL_TOKEN", hide_env_values = true)]
+    pub control_token: Option<String>,
+
+    #[command(flatten)]
+    pub session: session::SessionOpts,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct StrategyArgs {
+    /// Trading strategy, instantiated once per symbol
+    #[arg(long, value_enum, default_value_t = StrategyKind::Ma)]
+    pub strategy: StrategyKind,
+
+    /// Aggregate trades into bars and drive the strategy with bar closes instead of ticks
+    #[arg(long, value_enum)]
+    pub bar: Option<BarKind>,
+
+    /// Bar size: seconds for time bars, base qty for volume, quote notional for dollar, trades for tick
+    #[arg(long, default_value_t = 60.0)]
+    pub bar_size: f64,
+
+    /// Strategy timer period in milliseconds (0 disables on_timer)
+    #[arg(long, default_value_t = 1000)]
+    pub timer_ms: u64,
+
+    /// Moving average window size (number of trades)
+    #[arg(long, default_value_t = 50)]
+    pub ma_window: usize,
+
+    /// Moving average used for the entry/exit band
+    #[arg(long, value_enum, default_value_t = MaKind::Sma)]
+    pub ma_kind: MaKind,
+
+    /// Half-life in seconds for --ma-kind decay-ema (fast leg for crossover)
+    #[arg(long, default_value_t = 60.0)]
+    pub half_life_secs: f64,
+
+    /// Crossover: fast MA window
+    #[arg(long, default_value_t = 20)]
+    pub fast_window: usize,
+
+    /// Crossover: slow MA window
+    #[arg(long, default_value_t = 100)]
+    pub slow_window: usize,
+
+    /// Crossover: slow leg half-life in seconds for --ma-kind decay-ema
+    #[arg(long, default_value_t = 300.0)]
+    pub slow_half_life_secs: f64,
+
+    /// Bollinger: band width in standard deviations
+    #[arg(long, default_value_t = 2.0)]
+    pub bb_k: f64,
+
+    /// Threshold in basis points (e.g. 10 = 0.1%) to trigger entry/exit
+    #[arg(long, default_value_t = 10)]
+    pub threshold_bps: u32,
+
+    /// Drop trades whose aggregate id was already seen instead of trading on them
+    #[arg(long, default_value_t = false)]
+    pub dedupe: bool,
+
+    /// Never sell short: downside signals only close longs
+    #[arg(long, default_value_t = false)]
+    pub long_only: bool,
+
+    #[command(flatten)]
+    pub sizing: sizing::SizingOpts,
+
+    #[command(flatten)]
+    pub costs: costs::CostOpts,
+
+    #[command(flatten)]
+    pub exits: exits::ExitOpts,
+
+    #[command(flatten)]
+    pub risk: risk::RiskOpts,
+
+    #[command(flatten)]
+    pub health: health::HealthOpts,
+
+    /// Return interval in seconds for Sharpe/Sortino and equity-curve samples
+    #[arg(long, default_value_t = 60)]
+    pub stats_interval_secs: u64,
+
+    /// Write every paper fill to this file when the run ends (CSV, or JSON lines for .json/.ndjson)
+    #[arg(long)]
+    pub ledger: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct RecordArgs {
+    #[command(flatten)]
+    pub feed: FeedArgs,
+
+    #[command(flatten)]
+    pub out: recorder::RecordOpts,
+
+    /// Metrics server port
+    #[arg(long, default_value_t = 9000)]
+    pub metrics_port: u16,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ReplayArgs {
+    /// Recorded files, Binance aggTrades dumps (.csv, .zip) or directories of them; replayed in path order
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Symbol for aggTrades CSV dumps (default: from the file name, e.g. BTCUSDT-aggTrades-2024-01-01.zip)
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// Playback speed relative to recorded time (1 = real time, 10 = 10x); 0 = as fast as possible
+    #[arg(long, default_value_t = 0.0)]
+    pub speed: f64,
+
+    #[command(flatten)]
+    pub strategy: StrategyArgs,
+
+    /// Metrics server port
+    #[arg(long, default_value_t = 9000)]
+    pub metrics_port: u16,
+
+    /// Bearer token for the POST /control/* endpoints (disabled when unset)
+    #[arg(long, env = "QUANT_CONTROL_TOKEN", hide_env_values = true)]
+    pub control_token: Option<String>,
+
+    #[command(flatten)]
+    pub session: session::SessionOpts,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct BacktestArgs {
+    /// Recorded files, Binance aggTrades dumps (.csv, .zip) or directories of them; read in path order
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Symbol for aggTrades CSV dumps (default: from the file name, e.g. BTCUSDT-aggTrades-2024-01-01.zip)
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    #[command(flatten)]
+    pub strategy: StrategyArgs,
+
+    /// Also write the report as JSON to this path
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+
+    /// Don't print individual fills
+    #[arg(long, default_value_t = false)]
+    pub quiet: bool,
+}
+
+/// Market-data connection options shared by every mode that reads the live socket.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct FeedArgs {
+    /// Trading symbol(s), comma separated or repeated, e.g. btcusdt,ethusdt
+    #[arg(long, value_delimiter = ',', default_value = "btcusdt")]
+    pub symbol: Vec<String>,
+
+    /// Initial WS reconnect backoff in milliseconds (doubles per failed attempt)
+    #[arg(long, default_value_t = 500)]
+    pub ws_backoff_initial_ms: u64,
+
+    /// Maximum WS reconnect backoff in milliseconds
+    #[arg(long, default_value_t = 30_000)]
+    pub ws_backoff_max_ms: u64,
+
+    /// Rotate the WS connection after this many seconds (Binance disconnects at 24h)
+    #[arg(long, default_value_t = 23 * 3600)]
+    pub ws_max_session_secs: u64,
+
+    /// Reconnect if no WS message (data or ping) arrives for this many seconds
+    #[arg(long, default_value_t = 60)]
+    pub ws_idle_timeout_secs: u64,
+
+    /// Capacity of the reader -> strategy trade queue
+    #[arg(long, default_value_t = 4096)]
+    pub queue_capacity: usize,
+
+    /// What to do when the strategy falls behind and the trade queue is full
+    #[arg(long, value_enum, default_value_t = queue::Backpressure::DropNewest)]
+    pub backpressure: queue::Backpressure,
+}
+
+impl FeedArgs {
+    /// Lowercased, deduplicated symbol list.
+    pub fn symbols(&self) -> anyhow::Result<Vec<String>> {
+        let mut symbols: Vec<String> = self.symbol.iter().map(|s| s.trim().to_lowercase()).collect();
+        symbols.retain(|s| !s.is_empty());
+        symbols.sort();
+        symbols.dedup();
+        anyhow::ensure!(!symbols.is_empty(), "at least one --symbol is required");
+        Ok(symbols)
+    }
+
+    /// Raw stream for a single symbol, combined stream for a basket.
+    pub fn stream_url(&self) -> anyhow::Result<url::Url> {
+        let symbols = self.symbols()?;
+        let url = match symbols.as_slice() {
+            [one] => format!("wss://stream.binance.com:9443/ws/{one}@aggTrade"),
+            _ => {
+                let streams: Vec<String> = symbols.iter().map(|s| format!("{s}@aggTrade")).collect();
+                format!("wss://stream.binance.com:9443/stream?streams={}", streams.join("/"))
+            }
+        };
+        Ok(url::Url::parse(&url)?)
+    }
+}
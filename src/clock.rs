@@ -0,0 +1,37 @@
+//! Time source for the trading path. Live reads the wall clock; replay and
+//! backtests run on event time so results never depend on when or how fast
+//! they execute.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub trait Clock {
+    /// Current time in ms since epoch
+    fn now_ms(&self) -> u64;
+
+    /// Called with each trade's event time before it is processed
+    fn on_event(&mut self, _event_ms: u64) {}
+}
+
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+}
+
+/// Simulated clock that only moves forward with the data.
+#[derive(Default)]
+pub struct EventClock {
+    now_ms: u64,
+}
+
+impl Clock for EventClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms
+    }
+
+    fn on_event(&mut self, event_ms: u64) {
+        self.now_ms = self.now_ms.max(event_ms);
+    }
+}
@@ -0,0 +1,96 @@
+//! Operator control over HTTP: `POST /control/{pause,resume,flatten,kill}` on
+//! the metrics server. Requests need `Authorization: Bearer <token>` matching
+//! `--control-token`; without a token the endpoints answer 403. Commands go
+//! to the strategy loop, which applies them before its next event.
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Reject new entries; exits and stops still run
+    Pause,
+    Resume,
+    /// Close every open position now
+    Flatten,
+    /// Flatten and stop trading for good; the strategy loop exits
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingState {
+    Running,
+    Paused,
+    Killed,
+}
+
+impl TradingState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradingState::Running => "running",
+            TradingState::Paused => "paused",
+            TradingState::Killed => "killed",
+        }
+    }
+}
+
+/// A command and where to send the resulting state description.
+pub type Request = (ControlCommand, oneshot::Sender<String>);
+
+#[derive(Clone)]
+pub struct ControlState {
+    token: Option<Arc<str>>,
+    tx: mpsc::UnboundedSender<Request>,
+}
+
+pub fn channel(token: Option<String>) -> (ControlState, mpsc::UnboundedReceiver<Request>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (ControlState { token: token.map(Arc::from), tx }, rx)
+}
+
+pub fn router(state: ControlState) -> Router {
+    Router::new().route("/control/:action", post(handle)).with_state(state)
+}
+
+async fn handle(
+    State(state): State<ControlState>,
+    Path(action): Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, String) {
+    let Some(token) = &state.token else {
+        return (StatusCode::FORBIDDEN, "control disabled: start with --control-token\n".into());
+    };
+    let presented = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if !constant_time_eq(presented.as_bytes(), token.as_bytes()) {
+        return (StatusCode::UNAUTHORIZED, "bad or missing bearer token\n".into());
+    }
+    let cmd = match action.as_str() {
+        "pause" => ControlCommand::Pause,
+        "resume" => ControlCommand::Resume,
+        "flatten" => ControlCommand::Flatten,
+        "kill" => ControlCommand::Kill,
+        _ => return (StatusCode::NOT_FOUND, format!("unknown control action {action:?}\n")),
+    };
+    eprintln!("control: {action} requested");
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if state.tx.send((cmd, reply_tx)).is_err() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "strategy loop is not running\n".into());
+    }
+    match reply_rx.await {
+        Ok(msg) => (StatusCode::OK, msg + "\n"),
+        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "strategy loop is not running\n".into()),
+    }
+}
+
+// don't leak how much of the token matched through response timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,138 @@
+//! Execution costs for paper fills: exchange fees by tier and a slippage
+//! model. Paper orders cross the spread like market orders, so they pay the
+//! taker rate unless `--assume-maker` is set.
+
+use crate::indicators::RollingWindow;
+use clap::{Args as ClapArgs, ValueEnum};
+
+/// Binance spot VIP tiers (maker bps, taker bps) before the BNB discount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeeTier {
+    /// No fees
+    Zero,
+    /// 10.0 / 10.0 bps
+    Vip0,
+    /// 9.0 / 10.0 bps
+    Vip1,
+    /// 8.0 / 10.0 bps
+    Vip2,
+    /// 4.2 / 6.0 bps
+    Vip3,
+    /// 4.2 / 5.4 bps
+    Vip4,
+}
+
+impl FeeTier {
+    fn bps(self) -> (f64, f64) {
+        match self {
+            FeeTier::Zero => (0.0, 0.0),
+            FeeTier::Vip0 => (10.0, 10.0),
+            FeeTier::Vip1 => (9.0, 10.0),
+            FeeTier::Vip2 => (8.0, 10.0),
+            FeeTier::Vip3 => (4.2, 6.0),
+            FeeTier::Vip4 => (4.2, 5.4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SlippageKind {
+    /// Fill at the last trade price
+    None,
+    /// Pay --slippage-bps on every fill
+    Fixed,
+    /// Pay --impact-bps scaled by order qty / traded qty over the last --impact-window trades
+    Volume,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct CostOpts {
+    /// Exchange fee tier
+    #[arg(long, value_enum, default_value_t = FeeTier::Vip0)]
+    pub fee_tier: FeeTier,
+
+    /// Override the tier's maker fee, in bps
+    #[arg(long)]
+    pub maker_fee_bps: Option<f64>,
+
+    /// Override the tier's taker fee, in bps
+    #[arg(long)]
+    pub taker_fee_bps: Option<f64>,
+
+    /// Apply the 25% discount for paying fees in BNB
+    #[arg(long, default_value_t = false)]
+    pub bnb_discount: bool,
+
+    /// Charge the maker rate, as if every order rested on the book
+    #[arg(long, default_value_t = false)]
+    pub assume_maker: bool,
+
+    /// Slippage model for paper fills
+    #[arg(long, value_enum, default_value_t = SlippageKind::None)]
+    pub slippage: SlippageKind,
+
+    /// Slippage in bps for --slippage fixed
+    #[arg(long, default_value_t = 1.0)]
+    pub slippage_bps: f64,
+
+    /// Slippage in bps for an order as large as the recent traded volume (--slippage volume)
+    #[arg(long, default_value_t = 10.0)]
+    pub impact_bps: f64,
+
+    /// Trades in the recent-volume window for --slippage volume
+    #[arg(long, default_value_t = 100)]
+    pub impact_window: usize,
+}
+
+impl CostOpts {
+    /// Fee as a fraction of fill notional.
+    pub fn fee_rate(&self) -> f64 {
+        let (maker, taker) = self.fee_tier.bps();
+        let bps = if self.assume_maker {
+            self.maker_fee_bps.unwrap_or(maker)
+        } else {
+            self.taker_fee_bps.unwrap_or(taker)
+        };
+        let discount = if self.bnb_discount { 0.75 } else { 1.0 };
+        bps * discount / 10000.0
+    }
+}
+
+/// Per-symbol cost state; only the volume model needs market history.
+pub struct CostModel {
+    opts: CostOpts,
+    recent_qty: RollingWindow,
+}
+
+impl CostModel {
+    pub fn new(opts: &CostOpts) -> Self {
+        Self { opts: opts.clone(), recent_qty: RollingWindow::new(opts.impact_window) }
+    }
+
+    /// Feed every market trade's quantity.
+    pub fn observe(&mut self, qty: f64) {
+        if self.opts.slippage == SlippageKind::Volume {
+            self.recent_qty.push(qty);
+        }
+    }
+
+    pub fn fee_rate(&self) -> f64 {
+        self.opts.fee_rate()
+    }
+
+    /// Adverse price move in bps for an order of `qty`.
+    pub fn slippage_bps(&self, qty: f64) -> f64 {
+        match self.opts.slippage {
+            SlippageKind::None => 0.0,
+            SlippageKind::Fixed => self.opts.slippage_bps,
+            SlippageKind::Volume => {
+                let recent = self.recent_qty.sum();
+                if recent > 0.0 {
+                    self.opts.impact_bps * qty / recent
+                } else {
+                    self.opts.impact_bps
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,513 @@
+//! Per-trade strategy + paper trading core shared by live, replay and
+//! backtest. All time reads go through the injected `Clock`.
+
+use crate::bars::{Bar, BarBuilder};
+use crate::cli::StrategyArgs;
+use crate::clock::Clock;
+use crate::control::{ControlCommand, TradingState};
+use crate::costs::{CostModel, SlippageKind};
+use crate::exits::Exits;
+use crate::health::FeedHealth;
+use crate::metrics::{SymbolMetrics, METRICS};
+use crate::performance::Performance;
+use crate::position::{LedgerEntry, Position};
+use crate::risk::{Account, Risk};
+use crate::seq::{SeqEvent, SeqTracker};
+use crate::strategy::{self, Fill, OrderIntent, Strategy, Trade};
+use crate::AggTrade;
+use crate::snapshot::{BookSnapshot, Counters, Snapshot};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Per-symbol strategy instance and paper position, routed by `AggTrade.s`.
+pub struct SymbolState {
+    pub position: Position,
+    pub perf: Performance,
+    pub last_price: f64,
+    pub fills: u64,
+    /// Cost of slippage versus the last trade price, quote currency
+    pub slippage: f64,
+    costs: CostModel,
+    exits: Exits,
+    risk: Risk,
+    // risk rejections since the last accepted order; logs start/end of a burst
+    risk_burst: u64,
+    // limit behind the last logged rejection; a new one is logged mid-burst
+    risk_limit: Option<&'static str>,
+    strategy: Box<dyn Strategy>,
+    /// Set when the strategy consumes bars instead of individual trades
+    bars: Option<BarBuilder>,
+    // samples a fresh strategy needs before it is warm (`strategy::warmup_samples`)
+    warmup: usize,
+    // recent trades and closed bars, replayed into a fresh strategy on resume:
+    // trades for the strategy in tick mode and for the volume slippage window,
+    // bars for the strategy with --bar
+    recent: VecDeque<Trade>,
+    keep_trades: usize,
+    recent_bars: VecDeque<Bar>,
+}
+
+impl SymbolState {
+    fn new(args: &StrategyArgs, symbol: &str) -> Self {
+        let warmup = strategy::warmup_samples(args);
+        let impact = if args.costs.slippage == SlippageKind::Volume { args.costs.impact_window } else { 0 };
+        let keep_trades = if args.bar.is_some() { impact } else { warmup.max(impact) };
+        Self {
+            position: Position::default(),
+            perf: Performance::new(args.stats_interval_secs),
+            last_price: 0.0,
+            fills: 0,
+            slippage: 0.0,
+            costs: CostModel::new(&args.costs),
+            exits: Exits::new(&args.exits),
+            risk: Risk::new(&args.risk, symbol),
+            risk_burst: 0,
+            risk_limit: None,
+            strategy: strategy::build(args, symbol),
+            bars: args.bar.map(|kind| BarBuilder::new(kind, args.bar_size)),
+            warmup,
+            recent: VecDeque::new(),
+            keep_trades,
+            recent_bars: VecDeque::new(),
+        }
+    }
+
+    /// Net PnL marked at the last trade price
+    pub fn equity(&self) -> f64 {
+        self.position.net_pnl(self.last_price)
+    }
+
+    /// Mark equity and open notional into the per-symbol and account risk state.
+    fn mark_risk(&mut self, ts_ms: u64, account: &mut Account) {
+        let notional = self.position.qty.abs() * self.last_price;
+        self.risk.mark(ts_ms, self.equity(), notional, account);
+    }
+}
+
+/// Engine state every paper fill goes through, whichever book it is for.
+struct Desk {
+    clock: Box<dyn Clock + Send>,
+    account: Account,
+    ledger: Vec<LedgerEntry>,
+    log_fills: bool,
+}
+
+pub struct Engine {
+    args: StrategyArgs,
+    desk: Desk,
+    seq: SeqTracker,
+    // BTreeMap so timer callbacks run in a stable order (backtests must be deterministic)
+    books: BTreeMap<String, SymbolState>,
+    next_timer_ms: u64,
+    health: FeedHealth,
+    // why the feed is stale, while it is
+    stale: Option<String>,
+    trading: TradingState,
+}
+
+impl Engine {
+    pub fn new(args: StrategyArgs, clock: Box<dyn Clock + Send>) -> Self {
+        Self {
+            desk: Desk { clock, account: Account::default(), ledger: Vec::new(), log_fills: true },
+            seq: SeqTracker::default(),
+            books: BTreeMap::new(),
+            next_timer_ms: 0,
+            health: FeedHealth::new(&args.health),
+            stale: None,
+            trading: TradingState::Running,
+            args,
+        }
+    }
+
+    /// Print a line per paper fill (on by default)
+    pub fn set_log_fills(&mut self, on: bool) {
+        self.desk.log_fills = on;
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&SymbolState> {
+        self.books.get(symbol)
+    }
+
+    /// Books by symbol, in symbol order
+    pub fn books(&self) -> impl Iterator<Item = (&String, &SymbolState)> {
+        self.books.iter()
+    }
+
+    /// Every paper fill so far, in execution order
+    pub fn ledger(&self) -> &[LedgerEntry] {
+        &self.desk.ledger
+    }
+
+    pub fn on_trade(&mut self, tr: &AggTrade) {
+        self.desk.clock.on_event(tr.T);
+        self.poll_timer();
+        if self.seq.track(tr) == SeqEvent::Duplicate && self.args.dedupe {
+            return;
+        }
+        let now = self.desk.clock.now_ms();
+        let latency = self.health.on_message(now, tr.E);
+        METRICS.lock().unwrap().feed_latency_ms = latency;
+        self.update_health(now);
+
+        let trade = Trade {
+            ts_ms: tr.T,
+            price: tr.p.parse().unwrap_or(0.0),
+            qty: tr.q.parse().unwrap_or(0.0),
+            is_buyer_maker: tr.m,
+        };
+        let book = match self.books.get_mut(&tr.s) {
+            Some(b) => b,
+            None => {
+                let mut book = SymbolState::new(&self.args, &tr.s);
+                book.risk.halt = self.halt_reason();
+                self.books.entry(tr.s.clone()).or_insert(book)
+            }
+        };
+        book.last_price = trade.price;
+        book.costs.observe(trade.qty);
+        if book.keep_trades > 0 {
+            if book.recent.len() == book.keep_trades {
+                book.recent.pop_front();
+            }
+            book.recent.push_back(trade);
+        }
+        book.mark_risk(tr.T, &mut self.desk.account);
+
+        {
+            let mut g = METRICS.lock().unwrap();
+            let m = g.symbol(&tr.s);
+            m.trades += 1;
+            m.last_price = trade.price;
+        }
+
+        // protective exits go first; the strategy still sees the trade so its
+        // indicators stay current, but can't re-enter on the print an exit filled on
+        let exited = match book.exits.check(&book.position, trade.price, trade.ts_ms) {
+            Some(exit) => {
+                let filled = execute(&tr.s, book, vec![exit], tr.T, &mut self.desk);
+                if filled {
+                    METRICS.lock().unwrap().symbol(&tr.s).exits += 1;
+                }
+                filled
+            }
+            None => false,
+        };
+
+        let mut intents = match book.bars.as_mut() {
+            None => book.strategy.on_trade(&trade),
+            Some(builder) => match builder.on_trade(&trade) {
+                Some(bar) => on_bar(&tr.s, book, &bar),
+                None => Vec::new(),
+            },
+        };
+        if exited {
+            intents.clear();
+        }
+        execute(&tr.s, book, intents, tr.T, &mut self.desk);
+
+        // PnL and stats are re-marked on every trade, not only on fills
+        book.perf.observe(tr.T, book.equity(), book.position.qty != 0.0);
+        mark(METRICS.lock().unwrap().symbol(&tr.s), book);
+    }
+
+    /// Close time bars whose bucket has ended and fire `on_timer` on every
+    /// strategy if the clock passed the next timer boundary. Called per trade
+    /// and, live, from a wall-clock interval.
+    pub fn poll_timer(&mut self) {
+        let now = self.desk.clock.now_ms();
+        self.update_health(now);
+        if self.args.bar.is_some() {
+            for (symbol, book) in self.books.iter_mut() {
+                if let Some(bar) = book.bars.as_mut().and_then(|b| b.on_time(now)) {
+                    let intents = on_bar(symbol, book, &bar);
+                    execute(symbol, book, intents, now, &mut self.desk);
+                }
+            }
+        }
+
+        if self.args.timer_ms == 0 || now < self.next_timer_ms {
+            return;
+        }
+        self.next_timer_ms = now - now % self.args.timer_ms + self.args.timer_ms;
+        for (symbol, book) in self.books.iter_mut() {
+            let intents = book.strategy.on_timer(now);
+            execute(symbol, book, intents, now, &mut self.desk);
+        }
+    }
+}
+
+impl Engine {
+    /// Halt or resume entries on every book when the feed changes health,
+    /// flattening on the way into stale if configured.
+    fn update_health(&mut self, now: u64) {
+        let stale = self.health.check(now);
+        if stale.is_some() == self.stale.is_some() {
+            return;
+        }
+        match &stale {
+            Some(reason) => eprintln!("market data stale ({reason}), halting new entries"),
+            None => eprintln!("market data healthy again, resuming entries"),
+        }
+        METRICS.lock().unwrap().feed_stale = stale.is_some();
+        let flatten = stale.is_some() && self.health.flatten_on_stale();
+        self.stale = stale;
+        self.apply_halt();
+        if flatten {
+            self.flatten_all("stale_feed");
+        }
+    }
+
+    /// Apply an operator command; returns the resulting trading state.
+    pub fn control(&mut self, cmd: ControlCommand) -> TradingState {
+        match (cmd, self.trading) {
+            (_, TradingState::Killed) => {}
+            (ControlCommand::Pause, _) => self.trading = TradingState::Paused,
+            (ControlCommand::Resume, _) => self.trading = TradingState::Running,
+            (ControlCommand::Flatten, _) => self.flatten_all("operator_flatten"),
+            (ControlCommand::Kill, _) => {
+                self.flatten_all("operator_kill");
+                self.trading = TradingState::Killed;
+            }
+        }
+        eprintln!("control: {cmd:?} -> trading {}", self.trading.as_str());
+        METRICS.lock().unwrap().trading_state = self.trading as u8;
+        self.apply_halt();
+        self.trading
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut g = METRICS.lock().unwrap();
+        let books = self
+            .books
+            .iter()
+            .map(|(symbol, book)| {
+                let snap = BookSnapshot {
+                    position: book.position.clone(),
+                    last_price: book.last_price,
+                    fills: book.fills,
+                    slippage: book.slippage,
+                    perf: book.perf.clone(),
+                    exits: book.exits.state(),
+                    risk: book.risk.state(),
+                    counters: Counters::from_metrics(g.symbol(symbol)),
+                    recent: book.recent.iter().copied().collect(),
+                    recent_bars: book.recent_bars.iter().copied().collect(),
+                    bar: book.bars.as_ref().map(|b| b.state()),
+                };
+                (symbol.clone(), snap)
+            })
+            .collect();
+        Snapshot {
+            version: crate::snapshot::VERSION,
+            saved_ms: self.desk.clock.now_ms(),
+            strategy: self.args.strategy.name().to_string(),
+            books,
+        }
+    }
+
+    /// Rebuild books from a snapshot: accounting is restored directly, the
+    /// strategy is re-warmed on the saved trades or bars with its orders
+    /// discarded and then handed the position it holds.
+    pub fn restore(&mut self, snap: Snapshot) {
+        for (symbol, s) in snap.books {
+            let mut book = SymbolState::new(&self.args, &symbol);
+            for trade in &s.recent {
+                book.costs.observe(trade.qty);
+                if book.bars.is_none() {
+                    let _ = book.strategy.on_trade(trade);
+                }
+            }
+            for bar in &s.recent_bars {
+                let _ = book.strategy.on_bar(bar);
+            }
+            let samples = match book.bars.as_mut() {
+                None => s.recent.len(),
+                Some(builder) => {
+                    builder.restore(s.bar.unwrap_or_default());
+                    s.recent_bars.len()
+                }
+            };
+            if samples < book.warmup {
+                eprintln!(
+                    "[{symbol}] snapshot holds {samples} of {} warm-up samples, indicators stay cold until more arrive",
+                    book.warmup
+                );
+            }
+            book.strategy.on_restore(&s.position);
+            book.position = s.position;
+            book.last_price = s.last_price;
+            book.fills = s.fills;
+            book.slippage = s.slippage;
+            book.perf = s.perf;
+            book.exits.restore(s.exits);
+            book.risk.restore(s.risk);
+            book.risk.halt = self.halt_reason();
+            // other books' limits count this one before its next trade
+            let ts_ms = s.recent.last().map_or(snap.saved_ms, |t| t.ts_ms);
+            book.mark_risk(ts_ms, &mut self.desk.account);
+            book.recent = s.recent.into();
+            book.recent_bars = s.recent_bars.into();
+            {
+                let mut g = METRICS.lock().unwrap();
+                let m = g.symbol(&symbol);
+                s.counters.apply(m);
+                m.last_price = book.last_price;
+                mark(m, &book);
+            }
+            println!(
+                "Restored {symbol}: position={} pnl={:.2} fills={} ({samples} warm-up samples)",
+                book.position.qty,
+                book.equity(),
+                book.fills,
+            );
+            self.books.insert(symbol, book);
+        }
+    }
+
+    fn halt_reason(&self) -> Option<String> {
+        match self.trading {
+            TradingState::Running => self.stale.clone(),
+            TradingState::Paused => Some("paused by operator".to_string()),
+            TradingState::Killed => Some("killed by operator".to_string()),
+        }
+    }
+
+    fn apply_halt(&mut self) {
+        let halt = self.halt_reason();
+        for book in self.books.values_mut() {
+            book.risk.halt = halt.clone();
+        }
+    }
+
+    /// Close every open position at its book's last price.
+    pub fn flatten_all(&mut self, reason: &str) {
+        let now = self.desk.clock.now_ms();
+        for (symbol, book) in self.books.iter_mut() {
+            if let Some(close) = book.position.closing_order(book.last_price, reason.to_string()) {
+                execute(symbol, book, vec![close], now, &mut self.desk);
+            }
+        }
+    }
+}
+
+fn mark(m: &mut SymbolMetrics, book: &SymbolState) {
+    let pos = &book.position;
+    m.pnl = book.equity();
+    m.realized_pnl = pos.realized;
+    m.unrealized_pnl = pos.unrealized(book.last_price);
+    m.position = pos.qty;
+    m.avg_entry = pos.avg_price;
+    m.perf = book.perf.stats();
+}
+
+fn on_bar(symbol: &str, book: &mut SymbolState, bar: &Bar) -> Vec<OrderIntent> {
+    {
+        let mut g = METRICS.lock().unwrap();
+        let m = g.symbol(symbol);
+        m.bars += 1;
+        m.last_bar = *bar;
+    }
+    if book.recent_bars.len() == book.warmup {
+        book.recent_bars.pop_front();
+    }
+    book.recent_bars.push_back(*bar);
+    book.strategy.on_bar(bar)
+}
+
+/// Run each intent through the risk checks, paper-fill it at the book's last
+/// price plus slippage, charge the fee and notify the strategy.
+/// `event_ms` is when the triggering event happened, for decision latency.
+/// Returns whether any intent was filled.
+fn execute(symbol: &str, book: &mut SymbolState, intents: Vec<OrderIntent>, event_ms: u64, desk: &mut Desk) -> bool {
+    let mut filled = false;
+    for mut intent in intents {
+        // latency: now - trade time
+        let latency = desk.clock.now_ms().saturating_sub(event_ms);
+        {
+            let mut g = METRICS.lock().unwrap();
+            let m = g.symbol(symbol);
+            m.decisions += 1;
+            let _ = m.lat_hist.record(latency);
+        }
+        let price = loop {
+            let slip_bps = book.costs.slippage_bps(intent.qty);
+            let price = book.last_price * (1.0 + intent.side.sign() * slip_bps / 10000.0);
+            let pos = book.position.qty;
+            let Err(reason) = book.risk.check(&intent, pos, price, book.last_price, event_ms, &mut desk.account) else {
+                break Some(price);
+            };
+            METRICS.lock().unwrap().symbol(symbol).risk_rejections += 1;
+            if book.risk_burst == 0 || book.risk_limit != Some(reason.limit) {
+                eprintln!(
+                    "[{symbol}] risk rejected {} {:.6} ({}): {reason}",
+                    intent.side.as_str(),
+                    intent.qty,
+                    intent.reason
+                );
+                book.risk_limit = Some(reason.limit);
+            }
+            book.risk_burst += 1;
+            // a rejected flip still closes the side it was leaving
+            let after = pos + intent.side.sign() * intent.qty;
+            if pos != 0.0 && after != 0.0 && after.signum() != pos.signum() {
+                intent.qty = pos.abs();
+                continue;
+            }
+            break None;
+        };
+        let Some(price) = price else { continue };
+        filled = true;
+        if book.risk_burst > 1 {
+            eprintln!("[{symbol}] risk accepted an order again, {} intents rejected in last burst", book.risk_burst);
+        }
+        book.risk_burst = 0;
+        book.risk_limit = None;
+        let fee = price * intent.qty * book.costs.fee_rate();
+        let before = book.position.qty;
+        let applied = book.position.apply(intent.side, intent.qty, price, fee);
+        book.exits.on_fill(before, book.position.qty, price, event_ms);
+        book.fills += 1;
+        let slippage = (price - book.last_price).abs() * intent.qty;
+        book.slippage += slippage;
+        book.perf.on_fill(price * intent.qty, applied.round_trip);
+        book.mark_risk(event_ms, &mut desk.account);
+        let fill = Fill { side: intent.side, qty: intent.qty, price, fee };
+        book.strategy.on_fill(&fill);
+
+        let equity = book.equity();
+        {
+            let mut g = METRICS.lock().unwrap();
+            let m = g.symbol(symbol);
+            m.fills += 1;
+            m.fees += fee;
+            m.slippage += slippage;
+            mark(m, book);
+        }
+        if desk.log_fills {
+            println!(
+                "[{}] {} price={:.2} {} -> {} {:.6} fee={:.4} | equity={:.2}",
+                event_ms,
+                symbol,
+                price,
+                intent.reason,
+                intent.side.as_str(),
+                intent.qty,
+                fee,
+                equity
+            );
+        }
+        desk.ledger.push(LedgerEntry {
+            ts_ms: event_ms,
+            symbol: symbol.to_string(),
+            side: intent.side.as_str(),
+            qty: intent.qty,
+            price,
+            fee,
+            reason: intent.reason,
+            pos_after: book.position.qty,
+            avg_price_after: book.position.avg_price,
+            realized: applied.realized,
+            round_trip_pnl: applied.round_trip,
+        });
+    }
+    filled
+}
@@ -0,0 +1,92 @@
+//! Protective exits attached to the open position: stop-loss and take-profit
+//! in bps from the average entry, a trailing stop from the best price since
+//! entry, and a max holding time. The engine checks them on every trade
+//! before the strategy sees it and flattens when one triggers.
+
+use crate::position::Position;
+use crate::strategy::OrderIntent;
+use clap::Args as ClapArgs;
+use serde::{Deserialize, Serialize};
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ExitOpts {
+    /// Close when the position is this many bps against the entry price
+    #[arg(long)]
+    pub stop_loss_bps: Option<f64>,
+
+    /// Close when the position is this many bps in favour of the entry price
+    #[arg(long)]
+    pub take_profit_bps: Option<f64>,
+
+    /// Close when price retraces this many bps from the best price since entry
+    #[arg(long)]
+    pub trailing_stop_bps: Option<f64>,
+
+    /// Close positions held longer than this many seconds
+    #[arg(long)]
+    pub max_hold_secs: Option<u64>,
+}
+
+/// What `Exits` needs to resume tracking an open position after a restart.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ExitState {
+    pub opened_ms: u64,
+    pub best: f64,
+}
+
+/// Per-symbol exit state; follows the position through `on_fill`.
+pub struct Exits {
+    opts: ExitOpts,
+    opened_ms: u64,
+    // highest price since entry while long, lowest while short
+    best: f64,
+}
+
+impl Exits {
+    pub fn new(opts: &ExitOpts) -> Self {
+        Self { opts: opts.clone(), opened_ms: 0, best: 0.0 }
+    }
+
+    pub fn state(&self) -> ExitState {
+        ExitState { opened_ms: self.opened_ms, best: self.best }
+    }
+
+    pub fn restore(&mut self, state: ExitState) {
+        self.opened_ms = state.opened_ms;
+        self.best = state.best;
+    }
+
+    /// Track a fill that moved the position from `before` to `after`.
+    pub fn on_fill(&mut self, before: f64, after: f64, price: f64, ts_ms: u64) {
+        if after != 0.0 && (before == 0.0 || before.signum() != after.signum()) {
+            self.opened_ms = ts_ms;
+            self.best = price;
+        }
+    }
+
+    /// Closing order if any exit triggers at `price`.
+    pub fn check(&mut self, pos: &Position, price: f64, ts_ms: u64) -> Option<OrderIntent> {
+        if pos.qty == 0.0 || pos.avg_price <= 0.0 || price <= 0.0 {
+            return None;
+        }
+        let long = pos.qty > 0.0;
+        self.best = if long { self.best.max(price) } else { self.best.min(price) };
+        let dir = if long { 1.0 } else { -1.0 };
+        let pnl_bps = dir * (price / pos.avg_price - 1.0) * 10000.0;
+        let retrace_bps = -dir * (price / self.best - 1.0) * 10000.0;
+        let held_secs = ts_ms.saturating_sub(self.opened_ms) / 1000;
+
+        let reason = if self.opts.stop_loss_bps.is_some_and(|sl| pnl_bps <= -sl) {
+            "stop_loss"
+        } else if self.opts.take_profit_bps.is_some_and(|tp| pnl_bps >= tp) {
+            "take_profit"
+        } else if self.opts.trailing_stop_bps.is_some_and(|ts| retrace_bps >= ts) {
+            "trailing_stop"
+        } else if self.opts.max_hold_secs.is_some_and(|max| held_secs >= max) {
+            "max_hold"
+        } else {
+            return None;
+        };
+        pos.closing_order(price, format!("{reason} entry={:.2} pnl_bps={pnl_bps:.1}", pos.avg_price))
+    }
+}
@@ -0,0 +1,158 @@
+//! Binance WS reader: supervised connection loop with jittered exponential
+//! backoff, idle detection and proactive rotation ahead of the 24h cutoff.
+
+use crate::metrics::METRICS;
+use crate::{queue, recorder::Recorder, AggTrade};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::time::{sleep, sleep_until, timeout, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    pub url: url::Url,
+    pub backoff_initial: Duration,
+    pub backoff_max: Duration,
+    /// Reconnect proactively once a session is this old (Binance drops at 24h)
+    pub max_session: Duration,
+    /// Treat the socket as dead if nothing (data or ping) arrives for this long
+    pub idle_timeout: Duration,
+    /// Raw frame capture, written before parsing
+    pub recorder: Option<Recorder>,
+}
+
+/// Exponential backoff with jitter: delay is uniform in [cap/2, cap] where
+/// cap = min(initial * 2^attempt, max).
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, attempt: 0 }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let cap = self.initial.saturating_mul(1u32 << self.attempt.min(16)).min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        let ms = cap.as_millis() as u64;
+        Duration::from_millis(ms / 2 + fastrand::u64(0..=ms / 2))
+    }
+
+    fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+enum SessionEnd {
+    /// Session hit `max_session`; reconnect immediately
+    Rotate,
+    /// Server close, read error or idle timeout; reconnect after backoff
+    Dropped,
+    /// Strategy side hung up; stop the reader
+    ReceiverGone,
+}
+
+/// Parse a text frame into an `AggTrade`. Binance wraps combined streams into
+/// {stream, data}; raw /ws streams send the payload directly.
+pub fn parse_agg_trade(txt: &str) -> Option<AggTrade> {
+    let de: serde_json::Value = serde_json::from_str(txt).ok()?;
+    match de.get("data") {
+        Some(data) => serde_json::from_value::<AggTrade>(data.clone()).ok(),
+        None => serde_json::from_value::<AggTrade>(de).ok(),
+    }
+}
+
+fn set_connected(connected: bool) {
+    METRICS.lock().unwrap().ws_connected = connected;
+}
+
+/// Run the reader until the receiving side of `tx` is dropped.
+pub async fn run(cfg: FeedConfig, tx: queue::Sender) {
+    let mut backoff = Backoff::new(cfg.backoff_initial, cfg.backoff_max);
+    let mut first_attempt = true;
+    loop {
+        if !first_attempt {
+            METRICS.lock().unwrap().ws_reconnects += 1;
+        }
+        first_attempt = false;
+
+        match connect_async(cfg.url.clone()).await {
+            Ok((ws_stream, _)) => {
+                println!("WebSocket connected");
+                set_connected(true);
+                let end = session(ws_stream, &cfg, &tx, &mut backoff).await;
+                set_connected(false);
+                match end {
+                    SessionEnd::ReceiverGone => break,
+                    SessionEnd::Rotate => {
+                        println!("WS session reached {:?}, rotating connection", cfg.max_session);
+                        backoff.reset();
+                        continue;
+                    }
+                    SessionEnd::Dropped => {}
+                }
+            }
+            Err(e) => eprintln!("WS connect error: {e}"),
+        }
+
+        let delay = backoff.next_delay();
+        eprintln!("WS reconnecting in {} ms", delay.as_millis());
+        sleep(delay).await;
+    }
+    eprintln!("WS reader ended");
+}
+
+async fn session<S>(
+    ws_stream: tokio_tungstenite::WebSocketStream<S>,
+    cfg: &FeedConfig,
+    tx: &queue::Sender,
+    backoff: &mut Backoff,
+) -> SessionEnd
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (_write, mut read) = ws_stream.split();
+    let deadline = Instant::now() + cfg.max_session;
+    loop {
+        let msg = tokio::select! {
+            _ = sleep_until(deadline) => return SessionEnd::Rotate,
+            r = timeout(cfg.idle_timeout, read.next()) => match r {
+                Err(_) => {
+                    eprintln!("WS idle for {:?}, reconnecting", cfg.idle_timeout);
+                    return SessionEnd::Dropped;
+                }
+                Ok(None) => return SessionEnd::Dropped,
+                Ok(Some(msg)) => msg,
+            },
+        };
+        match msg {
+            Ok(Message::Text(txt)) => {
+                if let Some(r) = &cfg.recorder {
+                    r.record(&txt);
+                }
+                if let Some(t) = parse_agg_trade(&txt) {
+                    // data is flowing again, so the next failure starts from the initial delay
+                    backoff.reset();
+                    if tx.send(t).await.is_err() {
+                        return SessionEnd::ReceiverGone;
+                    }
+                }
+            }
+            Ok(Message::Binary(_)) => {}
+            Ok(Message::Ping(_)) => {}
+            Ok(Message::Pong(_)) => {}
+            Ok(Message::Frame(_)) => {}
+            Ok(Message::Close(frame)) => {
+                eprintln!("WS closed by server: {frame:?}");
+                return SessionEnd::Dropped;
+            }
+            Err(e) => {
+                eprintln!("WS error: {e}");
+                return SessionEnd::Dropped;
+            }
+        }
+    }
+}
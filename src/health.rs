@@ -0,0 +1,63 @@
+//! Market-data health: the feed is stale when the last trade's event time
+//! `E` lagged the clock by more than a threshold, or nothing arrived for a
+//! while. The engine halts new entries while stale and can flatten.
+//! Times come from the engine's clock, so replay and backtest judge the data
+//! against data time: a gap between recorded trades reads as a quiet feed.
+
+use clap::Args as ClapArgs;
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct HealthOpts {
+    /// Mark the feed stale when now - event time `E` of the last trade exceeds this
+    #[arg(long)]
+    pub max_feed_latency_ms: Option<u64>,
+
+    /// Mark the feed stale when no trade arrived for this many seconds
+    #[arg(long)]
+    pub feed_quiet_secs: Option<u64>,
+
+    /// Close open positions when the feed turns stale
+    #[arg(long, default_value_t = false)]
+    pub flatten_on_stale: bool,
+}
+
+pub struct FeedHealth {
+    opts: HealthOpts,
+    last_recv_ms: Option<u64>,
+    last_latency_ms: u64,
+}
+
+impl FeedHealth {
+    pub fn new(opts: &HealthOpts) -> Self {
+        Self { opts: opts.clone(), last_recv_ms: None, last_latency_ms: 0 }
+    }
+
+    pub fn flatten_on_stale(&self) -> bool {
+        self.opts.flatten_on_stale
+    }
+
+    /// Record a trade seen at `now_ms` with exchange event time `event_ms`;
+    /// returns its latency.
+    pub fn on_message(&mut self, now_ms: u64, event_ms: u64) -> u64 {
+        self.last_recv_ms = Some(now_ms);
+        self.last_latency_ms = now_ms.saturating_sub(event_ms);
+        self.last_latency_ms
+    }
+
+    /// Why the feed is stale at `now_ms`, if it is.
+    pub fn check(&self, now_ms: u64) -> Option<String> {
+        let last = self.last_recv_ms?;
+        if let Some(max) = self.opts.feed_quiet_secs {
+            let quiet = now_ms.saturating_sub(last);
+            if quiet > max * 1000 {
+                return Some(format!("no trade for {quiet} ms"));
+            }
+        }
+        if let Some(max) = self.opts.max_feed_latency_ms {
+            if self.last_latency_ms > max {
+                return Some(format!("feed latency {} ms", self.last_latency_ms));
+            }
+        }
+        None
+    }
+}
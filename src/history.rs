@@ -0,0 +1,296 @@
+//! Historical trade files for replay and backtest. Format is picked by name:
+//!
+//! - `*.zip`, `*.csv`, `*.csv.gz`: Binance public aggTrades dumps
+//!   (agg id, price, qty, first id, last id, time, is_buyer_maker, best match)
+//! - anything else: recorder output (`{"ts","frame"}`) or plain / combined-stream
+//!   aggTrade JSON lines, optionally gzipped
+//!
+//! Several symbols are read side by side and merged by trade time `T`, so
+//! the engine's clock sees one timeline.
+
+use crate::{feed, AggTrade};
+use flate2::read::MultiGzDecoder;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// One input line: (pacing timestamp in ms, trade), or None if it wasn't a trade.
+pub type Item = Option<(u64, AggTrade)>;
+
+/// Expand directories into their files and sort, so read order is stable.
+/// Binance dump and recorder names embed the date after the symbol, so this is
+/// chronological per symbol only; `for_each_merged` interleaves symbols.
+pub fn collect_files(inputs: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for p in inputs {
+        if p.is_dir() {
+            for entry in std::fs::read_dir(p)? {
+                let path = entry?.path();
+                if path.is_file() {
+                    files.push(path);
+                }
+            }
+        } else {
+            files.push(p.clone());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn open(path: &Path) -> anyhow::Result<Box<dyn BufRead>> {
+    let file = File::open(path).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+/// Call `f` for every line of `path` until it returns `Break`. `symbol` names
+/// the instrument in CSV dumps (which don't carry it); by default it is taken
+/// from the file name, e.g. `BTCUSDT-aggTrades-2024-01-01.zip`.
+pub fn for_each(
+    path: &Path,
+    symbol: Option<&str>,
+    mut f: impl FnMut(Item) -> ControlFlow<()>,
+) -> anyhow::Result<()> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let is_zip = name.ends_with(".zip");
+    if !is_zip && !name.ends_with(".csv") && !name.ends_with(".csv.gz") {
+        for line in open(path)?.lines() {
+            if f(parse_json_line(&line?)).is_break() {
+                break;
+            }
+        }
+        return Ok(());
+    }
+
+    let symbol = match symbol {
+        Some(s) => s.to_uppercase(),
+        None => symbol_from_file_name(path)
+            .ok_or_else(|| anyhow::anyhow!("{}: cannot infer symbol from file name, pass --symbol", path.display()))?,
+    };
+    if !is_zip {
+        return for_each_csv(open(path)?, &symbol, &mut f).map(drop);
+    }
+    let mut archive = zip::ZipArchive::new(File::open(path)?)
+        .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if !entry.name().to_lowercase().ends_with(".csv") {
+            continue;
+        }
+        if for_each_csv(BufReader::new(entry), &symbol, &mut f)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Files that form one chronological stream: dumps of one symbol, or
+/// recordings of one basket (`{prefix}-{start_ms}`). Anything else stands alone.
+fn stream_key(path: &Path) -> String {
+    if let Some(sym) = symbol_from_file_name(path) {
+        return sym;
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    match name.rsplit_once('-') {
+        Some((prefix, rest)) if rest.starts_with(|c: char| c.is_ascii_digit()) => prefix.to_string(),
+        _ => name,
+    }
+}
+
+/// Like `for_each` over all of `files`, but streams (see `stream_key`) are
+/// read concurrently and merged by trade time `T`, ties going to the earlier
+/// stream. Files within a stream keep path order.
+pub fn for_each_merged(
+    files: &[PathBuf],
+    symbol: Option<&str>,
+    mut f: impl FnMut(Item) -> ControlFlow<()>,
+) -> anyhow::Result<()> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for path in files {
+        groups.entry(stream_key(path)).or_default().push(path.clone());
+    }
+    let mut streams: Vec<Stream> = groups
+        .into_values()
+        .map(|paths| Stream::spawn(paths, symbol.map(str::to_string)))
+        .collect();
+
+    let mut heap = BinaryHeap::new();
+    let mut stopped = false;
+    for (i, stream) in streams.iter_mut().enumerate() {
+        stopped = stream.advance(&mut f)?.is_break();
+        if stopped {
+            break;
+        }
+        if let Some((_, t)) = &stream.head {
+            heap.push(Reverse((t.T, i)));
+        }
+    }
+    while let Some(Reverse((_, i))) = heap.pop().filter(|_| !stopped) {
+        let item = streams[i].head.take().expect("every heap entry has a head");
+        stopped = f(Some(item)).is_break() || streams[i].advance(&mut f)?.is_break();
+        if let Some((_, t)) = &streams[i].head {
+            heap.push(Reverse((t.T, i)));
+        }
+    }
+    // dropping the receivers stops readers that are still running
+    let handles: Vec<_> = streams.into_iter().filter_map(|s| s.handle).collect();
+    for h in handles {
+        h.join().map_err(|_| anyhow::anyhow!("history reader thread panicked"))??;
+    }
+    Ok(())
+}
+
+/// One stream's files, read on their own thread into a small buffer.
+struct Stream {
+    rx: mpsc::Receiver<Item>,
+    // next trade to merge, None once the stream is done
+    head: Option<(u64, AggTrade)>,
+    handle: Option<std::thread::JoinHandle<anyhow::Result<()>>>,
+}
+
+impl Stream {
+    fn spawn(paths: Vec<PathBuf>, symbol: Option<String>) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<Item>(1024);
+        let handle = std::thread::spawn(move || {
+            for path in &paths {
+                let mut hung_up = false;
+                for_each(path, symbol.as_deref(), |item| {
+                    hung_up = tx.send(item).is_err();
+                    if hung_up {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                })?;
+                if hung_up {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        Self { rx, head: None, handle: Some(handle) }
+    }
+
+    /// Load the next trade into `head`. Lines that weren't trades go
+    /// straight to `f`, which may stop the whole read.
+    fn advance(&mut self, f: &mut impl FnMut(Item) -> ControlFlow<()>) -> anyhow::Result<ControlFlow<()>> {
+        loop {
+            match self.rx.recv() {
+                Ok(Some(item)) => {
+                    self.head = Some(item);
+                    return Ok(ControlFlow::Continue(()));
+                }
+                Ok(None) => {
+                    if f(None).is_break() {
+                        return Ok(ControlFlow::Break(()));
+                    }
+                }
+                Err(_) => {
+                    // the reader finished: surface its error now, not after the merge
+                    if let Some(h) = self.handle.take() {
+                        h.join().map_err(|_| anyhow::anyhow!("history reader thread panicked"))??;
+                    }
+                    return Ok(ControlFlow::Continue(()));
+                }
+            }
+        }
+    }
+}
+
+fn for_each_csv(
+    reader: impl BufRead,
+    symbol: &str,
+    f: &mut impl FnMut(Item) -> ControlFlow<()>,
+) -> anyhow::Result<ControlFlow<()>> {
+    for line in reader.lines() {
+        let item = parse_csv_line(&line?, symbol).map(|t| (t.T, t));
+        if f(item).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// `BTCUSDT-aggTrades-2024-01-01.csv` -> `BTCUSDT`
+fn symbol_from_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let (sym, _) = name.split_once("-aggTrades")?;
+    (!sym.is_empty()).then(|| sym.to_uppercase())
+}
+
+/// Recorder lines pace by local receive time; bare trades fall back to the
+/// event time `E`.
+pub fn parse_json_line(line: &str) -> Item {
+    let v: serde_json::Value = serde_json::from_str(line).ok()?;
+    if let (Some(ts), Some(frame)) = (v.get("ts").and_then(|t| t.as_u64()), v.get("frame").and_then(|f| f.as_str())) {
+        return feed::parse_agg_trade(frame).map(|t| (ts, t));
+    }
+    let t = match v.get("data") {
+        Some(data) => serde_json::from_value::<AggTrade>(data.clone()).ok()?,
+        None => serde_json::from_value::<AggTrade>(v).ok()?,
+    };
+    Some((t.E, t))
+}
+
+/// One aggTrades CSV row. Header rows (present in newer dumps) fail to parse and
+/// are skipped. Spot dumps switched to microsecond timestamps in 2025; those
+/// are scaled back to ms. The dumps carry no event time, so `E` = `T`.
+pub fn parse_csv_line(line: &str, symbol: &str) -> Option<AggTrade> {
+    let mut cols = line.trim().split(',');
+    let a: u64 = cols.next()?.parse().ok()?;
+    let p = cols.next()?.to_string();
+    let q = cols.next()?.to_string();
+    let _first_id = cols.next()?;
+    let _last_id = cols.next()?;
+    let mut ts: u64 = cols.next()?.parse().ok()?;
+    if ts >= 10_000_000_000_000 {
+        ts /= 1000;
+    }
+    let m = parse_bool(cols.next()?)?;
+    let best = cols.next().and_then(parse_bool).unwrap_or(true);
+    Some(AggTrade {
+        e: "aggTrade".to_string(),
+        E: ts,
+        s: symbol.to_string(),
+        a,
+        p,
+        q,
+        T: ts,
+        m,
+        M: best,
+    })
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim() {
+        "True" | "true" | "1" => Some(true),
+        "False" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_skips_header_and_scales_microseconds() {
+        let header = "agg_trade_id,price,quantity,first_trade_id,last_trade_id,transact_time,is_buyer_maker,is_best_match";
+        assert!(parse_csv_line(header, "BTCUSDT").is_none());
+
+        let ms = parse_csv_line("26129,42000.10,0.015,27781,27782,1704067200123,True,True", "BTCUSDT").unwrap();
+        let us = parse_csv_line("26129,42000.10,0.015,27781,27782,1735689600123456,false,true\r", "BTCUSDT").unwrap();
+        assert_eq!((ms.a, ms.T, ms.E, ms.m), (26129, 1704067200123, 1704067200123, true));
+        assert_eq!((us.T, us.E, us.m), (1735689600123, 1735689600123, false));
+        assert_eq!((us.p.as_str(), us.q.as_str(), us.s.as_str()), ("42000.10", "0.015", "BTCUSDT"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Streaming indicators. Windows are fixed-capacity ring buffers with
+//! compensated running sums, so every update is O(1) regardless of window
+//! length and long sessions don't accumulate floating-point drift.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MaKind {
+    /// Simple average of the last N trade prices
+    Sma,
+    /// Exponential, alpha = 2 / (N + 1) per trade
+    Ema,
+    /// Linearly weighted, newest trade weighs N
+    Wma,
+    /// Exponential decay in wall/event time with a fixed half-life
+    DecayEma,
+    /// Volume-weighted average price over the last N trades
+    Vwap,
+}
+
+/// A moving average of trade prices; some kinds also use quantity or time.
+pub trait MovingAverage: Send {
+    /// Add a trade; the average once warmed up.
+    fn update(&mut self, price: f64, qty: f64, ts_ms: u64) -> Option<f64>;
+}
+
+pub fn build_ma(kind: MaKind, window: usize, half_life_secs: f64) -> Box<dyn MovingAverage> {
+    match kind {
+        MaKind::Sma => Box::new(Sma::new(window)),
+        MaKind::Ema => Box::new(Ema::new(window)),
+        MaKind::Wma => Box::new(Wma::new(window)),
+        MaKind::DecayEma => Box::new(DecayEma::new(half_life_secs)),
+        MaKind::Vwap => Box::new(Vwap::new(window)),
+    }
+}
+
+/// Neumaier-compensated running sum; supports removal by adding `-x`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct KahanSum {
+    sum: f64,
+    comp: f64,
+}
+
+impl KahanSum {
+    pub fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.comp += (self.sum - t) + x;
+        } else {
+            self.comp += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.comp
+    }
+}
+
+/// Last `capacity` values in a ring buffer with their running sum.
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    buf: Vec<f64>,
+    capacity: usize,
+    // next slot to overwrite once full
+    head: usize,
+    sum: KahanSum,
+}
+
+impl RollingWindow {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { buf: Vec::with_capacity(capacity), capacity, head: 0, sum: KahanSum::default() }
+    }
+
+    /// Append `x`, returning the value it evicted once the window is full.
+    pub fn push(&mut self, x: f64) -> Option<f64> {
+        self.sum.add(x);
+        if self.buf.len() < self.capacity {
+            self.buf.push(x);
+            return None;
+        }
+        let old = std::mem::replace(&mut self.buf[self.head], x);
+        self.head = (self.head + 1) % self.capacity;
+        self.sum.add(-old);
+        Some(old)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buf.len() == self.capacity
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum.value()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.buf.is_empty() {
+            0.0
+        } else {
+            self.sum() / self.buf.len() as f64
+        }
+    }
+}
+
+/// Simple moving average over the last `window` samples.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    win: RollingWindow,
+}
+
+impl Sma {
+    pub fn new(window: usize) -> Self {
+        Self { win: RollingWindow::new(window) }
+    }
+
+    /// Add a sample; the average once `window` samples have been seen.
+    pub fn update(&mut self, x: f64) -> Option<f64> {
+        self.win.push(x);
+        self.win.is_full().then(|| self.win.mean())
+    }
+}
+
+/// Rolling mean and population standard deviation over the last `window`
+/// samples. Values are shifted by the first sample before squaring so prices
+/// like 60000 +/- 5 don't lose the variance to cancellation.
+#[derive(Debug, Clone)]
+pub struct RollingStd {
+    win: RollingWindow,
+    sum_sq: KahanSum,
+    shift: Option<f64>,
+}
+
+impl RollingStd {
+    pub fn new(window: usize) -> Self {
+        Self { win: RollingWindow::new(window), sum_sq: KahanSum::default(), shift: None }
+    }
+
+    /// Add a sample; (mean, std) once `window` samples have been seen.
+    pub fn update(&mut self, x: f64) -> Option<(f64, f64)> {
+        let shift = *self.shift.get_or_insert(x);
+        let d = x - shift;
+        if let Some(old) = self.win.push(d) {
+            self.sum_sq.add(-old * old);
+        }
+        self.sum_sq.add(d * d);
+        self.current()
+    }
+
+    /// (mean, std) of the current window, without adding a sample.
+    pub fn current(&self) -> Option<(f64, f64)> {
+        let shift = self.shift?;
+        if !self.win.is_full() {
+            return None;
+        }
+        let n = self.win.capacity as f64;
+        let mean = self.win.sum() / n;
+        let var = (self.sum_sq.value() / n - mean * mean).max(0.0);
+        Some((shift + mean, var.sqrt()))
+    }
+}
+
+impl MovingAverage for Sma {
+    fn update(&mut self, price: f64, _qty: f64, _ts_ms: u64) -> Option<f64> {
+        Sma::update(self, price)
+    }
+}
+
+/// Per-sample EMA with alpha = 2 / (window + 1); reports after `window` samples.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f64,
+    warmup: usize,
+    seen: usize,
+    value: f64,
+}
+
+impl Ema {
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self { alpha: 2.0 / (window as f64 + 1.0), warmup: window, seen: 0, value: 0.0 }
+    }
+}
+
+impl MovingAverage for Ema {
+    fn update(&mut self, price: f64, _qty: f64, _ts_ms: u64) -> Option<f64> {
+        self.value = if self.seen == 0 { price } else { self.value + self.alpha * (price - self.value) };
+        self.seen = self.seen.saturating_add(1);
+        (self.seen >= self.warmup).then_some(self.value)
+    }
+}
+
+/// Linearly weighted MA: weights 1..=n from oldest to newest. The weighted sum
+/// is updated in O(1): when full, every weight drops by one (subtract the plain
+/// sum) and the new sample enters with weight n.
+#[derive(Debug, Clone)]
+pub struct Wma {
+    win: RollingWindow,
+    weighted: KahanSum,
+}
+
+impl Wma {
+    pub fn new(window: usize) -> Self {
+        Self { win: RollingWindow::new(window), weighted: KahanSum::default() }
+    }
+}
+
+impl MovingAverage for Wma {
+    fn update(&mut self, price: f64, _qty: f64, _ts_ms: u64) -> Option<f64> {
+        let n = self.win.capacity as f64;
+        if self.win.is_full() {
+            self.weighted.add(-self.win.sum());
+            self.weighted.add(n * price);
+        } else {
+            self.weighted.add((self.win.buf.len() + 1) as f64 * price);
+        }
+        self.win.push(price);
+        self.win.is_full().then(|| self.weighted.value() / (n * (n + 1.0) / 2.0))
+    }
+}
+
+/// EMA whose weight decays with elapsed time rather than trade count, so a
+/// burst of trades doesn't move it faster than a quiet minute would.
+/// Reports once one half-life has passed since the first sample.
+#[derive(Debug, Clone)]
+pub struct DecayEma {
+    half_life_ms: f64,
+    first_ts: Option<u64>,
+    last_ts: u64,
+    value: f64,
+}
+
+impl DecayEma {
+    pub fn new(half_life_secs: f64) -> Self {
+        Self { half_life_ms: (half_life_secs * 1000.0).max(1.0), first_ts: None, last_ts: 0, value: 0.0 }
+    }
+}
+
+impl MovingAverage for DecayEma {
+    fn update(&mut self, price: f64, _qty: f64, ts_ms: u64) -> Option<f64> {
+        let Some(first) = self.first_ts else {
+            self.first_ts = Some(ts_ms);
+            self.last_ts = ts_ms;
+            self.value = price;
+            return None;
+        };
+        let dt = ts_ms.saturating_sub(self.last_ts) as f64;
+        let alpha = 1.0 - (-std::f64::consts::LN_2 * dt / self.half_life_ms).exp();
+        self.value += alpha * (price - self.value);
+        self.last_ts = self.last_ts.max(ts_ms);
+        (ts_ms.saturating_sub(first) as f64 >= self.half_life_ms).then_some(self.value)
+    }
+}
+
+/// Rolling VWAP over the last `window` trades: sum(p * q) / sum(q).
+#[derive(Debug, Clone)]
+pub struct Vwap {
+    notional: RollingWindow,
+    volume: RollingWindow,
+}
+
+impl Vwap {
+    pub fn new(window: usize) -> Self {
+        Self { notional: RollingWindow::new(window), volume: RollingWindow::new(window) }
+    }
+}
+
+impl MovingAverage for Vwap {
+    fn update(&mut self, price: f64, qty: f64, _ts_ms: u64) -> Option<f64> {
+        self.notional.push(price * qty);
+        self.volume.push(qty);
+        let vol = self.volume.sum();
+        (self.volume.is_full() && vol > 0.0).then(|| self.notional.sum() / vol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deterministic prices around 60000 with small moves
+    fn prices(n: usize) -> Vec<f64> {
+        (0..n).map(|i| 60000.0 + ((i * 7919) % 101) as f64 * 0.37 - 18.0).collect()
+    }
+
+    #[test]
+    fn rolling_window_matches_naive_after_wrap() {
+        let xs = prices(1000);
+        let mut win = RollingWindow::new(50);
+        for (i, &x) in xs.iter().enumerate() {
+            win.push(x);
+            let tail = &xs[(i + 1).saturating_sub(50)..=i];
+            let naive: f64 = tail.iter().sum();
+            assert!((win.sum() - naive).abs() < 1e-6, "sum at {i}");
+            assert!((win.mean() - naive / tail.len() as f64).abs() < 1e-9, "mean at {i}");
+        }
+    }
+
+    #[test]
+    fn wma_matches_naive_after_wrap() {
+        let xs = prices(1000);
+        let n = 30;
+        let mut wma = Wma::new(n);
+        for (i, &x) in xs.iter().enumerate() {
+            let got = wma.update(x, 1.0, 0);
+            if i + 1 < n {
+                assert!(got.is_none());
+                continue;
+            }
+            let tail = &xs[i + 1 - n..=i];
+            let weighted: f64 = tail.iter().enumerate().map(|(k, p)| (k + 1) as f64 * p).sum();
+            let naive = weighted / (n * (n + 1) / 2) as f64;
+            assert!((got.unwrap() - naive).abs() < 1e-8, "wma at {i}");
+        }
+    }
+}
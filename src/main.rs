@@ -1,64 +1,44 @@
 
+mod backtest;
+mod bars;
+mod cli;
+mod clock;
+mod control;
+mod costs;
+mod engine;
+mod exits;
+mod feed;
+mod health;
+mod history;
+mod indicators;
+mod metrics;
+mod performance;
+mod position;
+mod queue;
+mod recorder;
+mod replay;
+mod risk;
+mod seq;
+mod session;
+mod sizing;
+mod snapshot;
+mod strategy;
+
 use axum::{routing::get, Router};
 use clap::Parser;
-use futures::StreamExt;
-use hdrhistogram::Histogram;
-use once_cell::sync::Lazy;
+use control::TradingState;
+use session::SessionOpts;
+use cli::{Args, BacktestArgs, Cli, Command, FeedArgs, RecordArgs, ReplayArgs, StrategyArgs};
+use metrics::{metrics_handler, METRICS};
+use recorder::Recorder;
 use serde::Deserialize;
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-
-static METRICS: Lazy<Arc<Mutex<Metrics>>> = Lazy::new(|| Arc::new(Mutex::new(Metrics::default())));
-
-struct Metrics {
-    trades: u64,
-    decisions: u64,
-    fills: u64,
-    pnl: f64,
-    
-    last_price: f64,
-    // latency from trade timestamp to decision time (ms)
-    lat_hist: Histogram<u64>,
-}
-
-impl Default for Metrics {
-    fn default() -> Self {
-        Self {
-            trades: 0,
-            decisions: 0,
-            fills: 0,
-            pnl: 0.0,
-            last_price: 0.0,
-            lat_hist: Histogram::<u64>::new(3).unwrap(),
-        }
-    }
-}
-
-#[derive(Parser, Debug, Clone)]
-#[command(name = "quant-mini")]
-#[command(about = "Binance WS -> MA strategy -> paper trading -> metrics")]
-struct Args {
-    /// Trading symbol, e.g. btcusdt
-    #[arg(long, default_value = "btcusdt")]
-    symbol: String,
-
-    /// Moving average window size (number of trades)
-    #[arg(long, default_value_t = 50)]
-    ma_window: usize,
-
-    /// Threshold in basis points (e.g. 10 = 0.1%) to trigger entry/exit
-    #[arg(long, default_value_t = 10)]
-    threshold_bps: u32,
-
-    /// Metrics server port
-    #[arg(long, default_value_t = 9000)]
-    metrics_port: u16,
-}
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 
+#[allow(non_snake_case)]
 #[derive(Debug, Deserialize, Clone)]
 struct AggTrade {
+    #[allow(dead_code)]
     e: String,
     E: u64,
     s: String,
@@ -73,153 +53,276 @@ struct AggTrade {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
-    let symbol = args.symbol.to_lowercase();
-    let stream_url = format!("wss://stream.binance.com:9443/ws/{}@aggTrade", symbol);
-    println!("Connecting to: {}", &stream_url);
-
-    // spawn metrics server
-    let metrics_app = Router::new().route("/metrics", get(metrics_handler));
-    let metrics_port = args.metrics_port;
-    tokio::spawn(async move {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Record(rec)) => run_record(rec).await,
+        Some(Command::Replay(rep)) => run_replay(rep).await,
+        Some(Command::Backtest(bt)) => run_backtest(bt),
+        None => run_live(cli.live).await,
+    }
+}
+
+/// Running metrics server; `shutdown` finishes in-flight requests and stops it.
+struct MetricsServer {
+    stop: oneshot::Sender<()>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl MetricsServer {
+    async fn shutdown(self) {
+        let _ = self.stop.send(());
+        let _ = self.handle.await;
+    }
+}
+
+/// Serve `/metrics`, plus the control endpoints when a strategy loop runs.
+fn spawn_metrics_server(metrics_port: u16, control: Option<control::ControlState>) -> MetricsServer {
+    let mut metrics_app = Router::new().route("/metrics", get(metrics_handler));
+    if let Some(control) = control {
+        metrics_app = metrics_app.merge(control::router(control));
+    }
+    let (stop, stopped) = oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
         let addr: std::net::SocketAddr = format!("0.0.0.0:{metrics_port}").parse().unwrap();
         println!("Metrics on http://{addr}/metrics");
         let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-        axum::serve(listener, metrics_app).await.unwrap();
+        axum::serve(listener, metrics_app)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .await
+            .unwrap();
     });
+    MetricsServer { stop, handle }
+}
 
-    let (tx, mut rx) = mpsc::channel::<AggTrade>(4096);
-
-    // WS reader task
-    let url = url::Url::parse(&stream_url)?;
-    tokio::spawn(async move {
-        let (ws_stream, _) = connect_async(url).await.expect("WS connect");
-        println!("WebSocket connected");
-        let (_write, mut read) = ws_stream.split();
-        while let Some(msg) = read.next().await {
-            match msg {
-                Ok(Message::Text(txt)) => {
-                    // Binance sometimes wraps into {stream, data}, sometimes direct (for /ws)
-                    let de: serde_json::Value = serde_json::from_str(&txt).unwrap_or_default();
-                    let trade_opt = if de.get("data").is_some() {
-                        serde_json::from_value::<AggTrade>(de.get("data").unwrap().clone()).ok()
-                    } else {
-                        serde_json::from_value::<AggTrade>(de.clone()).ok()
-                    };
-                    if let Some(t) = trade_opt {
-                        let _ = tx.try_send(t);
-                    }
-                }
-                Ok(Message::Binary(_)) => {}
-                Ok(Message::Ping(_)) => {}
-                Ok(Message::Pong(_)) => {}
-                Ok(Message::Frame(_)) => {}
-                Ok(Message::Close(_)) => break,
-                Err(e) => {
-                    eprintln!("WS error: {e}");
-                    break;
-                }
-            }
+/// Resolves on Ctrl-C (SIGINT) or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
         }
-        eprintln!("WS reader ended");
-    });
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
 
-    // Strategy + paper trader
-    let mut prices: Vec<f64> = Vec::new();
-    let mut pos_qty: f64 = 0.0;
-    let mut cash: f64 = 0.0;
-
-    while let Some(tr) = rx.recv().await {
-        let price: f64 = tr.p.parse().unwrap_or(0.0);
-        let _qty: f64 = tr.q.parse().unwrap_or(0.0);
-        prices.push(price);
-        if prices.len() > args.ma_window {
-            prices.remove(0);
-        }
+/// Pre-create per-symbol series so they are scraped as 0 before the first trade.
+fn register_symbols(strategy: &str, symbols: &[String]) {
+    let mut m = METRICS.lock().unwrap();
+    m.strategy = strategy.to_string();
+    for s in symbols {
+        m.symbol(&s.to_uppercase());
+    }
+}
 
-        // metrics update
-        {
-            let mut m = METRICS.lock().unwrap();
-            m.trades += 1;
-            m.last_price = price;
-        }
+/// Start the WS reader task; parsed trades come out of the returned queue.
+fn spawn_feed(
+    feed: &FeedArgs,
+    recorder: Option<Recorder>,
+) -> anyhow::Result<(tokio::task::JoinHandle<()>, queue::Receiver)> {
+    let url = feed.stream_url()?;
+    println!("Connecting to: {}", &url);
+    let (tx, rx) = queue::channel(feed.queue_capacity, feed.backpressure);
+    let feed_cfg = feed::FeedConfig {
+        url,
+        backoff_initial: Duration::from_millis(feed.ws_backoff_initial_ms),
+        backoff_max: Duration::from_millis(feed.ws_backoff_max_ms),
+        max_session: Duration::from_secs(feed.ws_max_session_secs),
+        idle_timeout: Duration::from_secs(feed.ws_idle_timeout_secs),
+        recorder,
+    };
+    Ok((tokio::spawn(feed::run(feed_cfg, tx)), rx))
+}
 
-        if prices.len() < args.ma_window {
-            continue;
-        }
-        let ma: f64 = prices.iter().sum::<f64>() / prices.len() as f64;
-        let up = ma * (1.0 + args.threshold_bps as f64 / 10000.0);
-        let dn = ma * (1.0 - args.threshold_bps as f64 / 10000.0);
-
-        let mut decision: Option<&'static str> = None;
-        if pos_qty <= 0.0 && price > up {
-            decision = Some("BUY");
-        } else if pos_qty > 0.0 && price < dn {
-            decision = Some("SELL");
+/// Capture raw frames until SIGINT/SIGTERM. Trades are still parsed so sequence gaps
+/// in the recording show up in metrics.
+async fn run_record(args: RecordArgs) -> anyhow::Result<()> {
+    let symbols = args.feed.symbols()?;
+    let server = spawn_metrics_server(args.metrics_port, None);
+    register_symbols("none", &symbols);
+
+    let (recorder, writer) = recorder::spawn(args.out.clone(), symbols.join("_"))?;
+    let (reader, mut rx) = spawn_feed(&args.feed, Some(recorder))?;
+    let mut seq = seq::SeqTracker::default();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            tr = rx.recv() => {
+                let Some(tr) = tr else { break };
+                seq.track(&tr);
+                let mut g = METRICS.lock().unwrap();
+                g.queue_depth = rx.len();
+                let m = g.symbol(&tr.s);
+                m.trades += 1;
+                m.last_price = tr.p.parse().unwrap_or(0.0);
+            }
         }
+    }
 
-        if let Some(side) = decision {
-            // latency: now - trade time
-            let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
-            let latency = now_ms.saturating_sub(tr.T);
-            {
-                let mut m = METRICS.lock().unwrap();
-                m.decisions += 1;
-                let _ = m.lat_hist.record(latency);
+    // dropping the reader releases the last Recorder handle, which closes the file
+    reader.abort();
+    let _ = reader.await;
+    tokio::task::spawn_blocking(move || writer.join())
+        .await?
+        .map_err(|_| anyhow::anyhow!("recorder thread panicked"))?;
+    println!("Recording stopped");
+    server.shutdown().await;
+    Ok(())
+}
+
+async fn run_live(args: Args) -> anyhow::Result<()> {
+    let symbols = args.feed.symbols()?;
+    let (control, control_rx) = control::channel(args.control_token.clone());
+    let server = spawn_metrics_server(args.metrics_port, Some(control));
+    register_symbols(args.strategy.strategy.name(), &symbols);
+    let (reader, rx) = spawn_feed(&args.feed, None)?;
+    let clock = Box::new(clock::WallClock);
+    run_strategy(&args.strategy, &args.session, clock, rx, control_rx, Some(reader.abort_handle())).await?;
+    let _ = reader.await;
+    server.shutdown().await;
+    Ok(())
+}
+
+/// Replay recorded files through the same queue and strategy loop as live.
+/// Always blocks on a full queue and runs the engine on event time, so results
+/// are reproducible and match `backtest` whatever the `--speed`.
+async fn run_replay(args: ReplayArgs) -> anyhow::Result<()> {
+    let files = history::collect_files(&args.files)?;
+    anyhow::ensure!(!files.is_empty(), "no files to replay");
+    let (control, control_rx) = control::channel(args.control_token.clone());
+    let server = spawn_metrics_server(args.metrics_port, Some(control));
+    register_symbols(args.strategy.strategy.name(), &[]);
+
+    let (tx, rx) = queue::channel(4096, queue::Backpressure::Block);
+    let reader = replay::spawn(files, args.symbol.clone(), args.speed, tx);
+    // the blocking reader can't be aborted; it stops once the strategy side hangs up
+    let clock = Box::new(clock::EventClock::default());
+    run_strategy(&args.strategy, &args.session, clock, rx, control_rx, None).await?;
+    let stats = reader.await??;
+    println!(
+        "Replay finished: {} trades from {} files ({} lines skipped)",
+        stats.trades, stats.files, stats.skipped
+    );
+    server.shutdown().await;
+    Ok(())
+}
+
+fn run_backtest(args: BacktestArgs) -> anyhow::Result<()> {
+    let report = backtest::run(&args)?;
+    backtest::print(&report);
+    if let Some(path) = &args.report {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn save_snapshot(engine: &engine::Engine, session: &SessionOpts) {
+    let Some(path) = &session.snapshot else { return };
+    if let Err(e) = snapshot::save(path, &engine.snapshot()) {
+        eprintln!("snapshot to {} failed: {e}", path.display());
+    }
+}
+
+/// Live/replay strategy loop: drains `rx` into the engine until every sender
+/// is gone, an operator kills trading or a shutdown signal arrives. On a
+/// signal the `reader` is stopped and trades already queued are still
+/// processed before the session summary.
+async fn run_strategy(
+    args: &StrategyArgs,
+    session: &SessionOpts,
+    clock: Box<dyn clock::Clock + Send>,
+    mut rx: queue::Receiver,
+    mut control_rx: mpsc::UnboundedReceiver<control::Request>,
+    reader: Option<tokio::task::AbortHandle>,
+) -> anyhow::Result<()> {
+    let mut engine = engine::Engine::new(args.clone(), clock);
+    if let (true, Some(path)) = (session.resume, &session.snapshot) {
+        match snapshot::load(path)? {
+            Some(snap) => {
+                anyhow::ensure!(
+                    snap.strategy == args.strategy.name(),
+                    "{}: snapshot is for strategy {:?}, running {:?}",
+                    path.display(),
+                    snap.strategy,
+                    args.strategy.name()
+                );
+                engine.restore(snap);
             }
-            // paper fill at current price
-            match side {
-                "BUY" => {
-                    pos_qty = 1.0;
-                    cash -= price * pos_qty;
-                }
-                "SELL" => {
-                    cash += price * pos_qty;
-                    pos_qty = 0.0;
-                }
-                _ => {}
+            None => println!("No snapshot at {}, starting fresh", path.display()),
+        }
+    }
+    // wakes the engine so strategy timers fire even when no trades arrive
+    let period_ms = match args.timer_ms {
+        0 => 1000,
+        t => t.min(1000),
+    };
+    let mut timer = tokio::time::interval(Duration::from_millis(period_ms));
+    let mut snapshot_timer = tokio::time::interval(Duration::from_secs(session.snapshot_secs.max(1)));
+    snapshot_timer.tick().await;
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut reason = "feed_ended";
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => {
+                println!("Shutdown signal received, stopping");
+                reason = "signal";
+                break;
             }
-            let equity = cash + pos_qty * price;
-            {
-                let mut m = METRICS.lock().unwrap();
-                m.fills += 1;
-                m.pnl = equity; // start from 0 cash, equity equals PnL
+            tr = rx.recv() => {
+                let Some(tr) = tr else { break };
+                METRICS.lock().unwrap().queue_depth = rx.len();
+                engine.on_trade(&tr);
+            }
+            _ = timer.tick() => engine.poll_timer(),
+            _ = snapshot_timer.tick(), if session.snapshot.is_some() => save_snapshot(&engine, session),
+            Some((cmd, reply)) = control_rx.recv() => {
+                let state = engine.control(cmd);
+                let _ = reply.send(format!("trading {}", state.as_str()));
+                if state == TradingState::Killed {
+                    println!("Trading killed by operator");
+                    reason = "killed";
+                    break;
+                }
             }
-            println!("[{}] price={:.2} ma={:.2} -> {} | equity={:.2}", tr.T, price, ma, side, equity);
         }
     }
 
-    Ok(())
-}
+    // with the reader gone its sender drops, so recv ends once the queue is empty
+    if let Some(reader) = reader {
+        reader.abort();
+        let mut drained = 0u64;
+        while let Some(tr) = rx.recv().await {
+            engine.on_trade(&tr);
+            drained += 1;
+        }
+        if drained > 0 {
+            println!("Drained {drained} queued trades");
+        }
+    }
+    if session.flatten_on_exit {
+        engine.flatten_all("session_end");
+    }
 
-async fn metrics_handler() -> String {
-    let m = METRICS.lock().unwrap();
-    let p50 = m.lat_hist.value_at_quantile(0.50);
-    let p90 = m.lat_hist.value_at_quantile(0.90);
-    let p99 = m.lat_hist.value_at_quantile(0.99);
-    format!(
-        concat!(
-        "# HELP quant_trades_total Number of trades processed\n",
-        "# TYPE quant_trades_total counter\n",
-        "quant_trades_total {}\n",
-        "# HELP quant_decisions_total Decisions made by strategy\n",
-        "# TYPE quant_decisions_total counter\n",
-        "quant_decisions_total {}\n",
-        "# HELP quant_fills_total Paper fills\n",
-        "# TYPE quant_fills_total counter\n",
-        "quant_fills_total {}\n",
-        "# HELP quant_pnl Equity value as PnL baseline\n",
-        "# TYPE quant_pnl gauge\n",
-        "quant_pnl {}\n",
-        "# HELP quant_last_price Last trade price\n",
-        "# TYPE quant_last_price gauge\n",
-        "quant_last_price {}\n",
-        "# HELP quant_latency_ms Decision latency histogram (p50/p90/p99)\n",
-        "# TYPE quant_latency_ms summary\n",
-        "quant_latency_ms{{quantile=\"0.50\"}} {}\n",
-        "quant_latency_ms{{quantile=\"0.90\"}} {}\n",
-        "quant_latency_ms{{quantile=\"0.99\"}} {}\n",
-        ),
-        m.trades, m.decisions, m.fills, m.pnl, m.last_price, p50, p90, p99
-    )
+    save_snapshot(&engine, session);
+
+    let summary = session::Summary::collect(&engine, reason);
+    summary.print();
+    if let Some(path) = &session.summary {
+        std::fs::write(path, serde_json::to_string_pretty(&summary)?)?;
+        println!("Summary written to {}", path.display());
+    }
+    if let Some(path) = &args.ledger {
+        position::write_ledger(path, engine.ledger())?;
+        println!("Ledger written to {}", path.display());
+    }
+    Ok(())
 }
@@ -0,0 +1,217 @@
+//! Process-wide metrics and Prometheus text rendering. Per-instrument series
+//! carry `symbol` and `strategy` labels; connection-level series (WS state,
+//! queue depth) are shared by every symbol and stay unlabeled.
+
+use crate::bars::Bar;
+use crate::performance::Stats;
+use hdrhistogram::Histogram;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+pub static METRICS: Lazy<Arc<Mutex<Metrics>>> = Lazy::new(|| Arc::new(Mutex::new(Metrics::default())));
+
+pub struct SymbolMetrics {
+    pub trades: u64,
+    pub decisions: u64,
+    pub fills: u64,
+    pub pnl: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub position: f64,
+    pub avg_entry: f64,
+    pub perf: Stats,
+    pub fees: f64,
+    pub slippage: f64,
+
+    pub last_price: f64,
+    // latency from trade timestamp to decision time (ms)
+    pub lat_hist: Histogram<u64>,
+
+    // aggTrade id sequence anomalies
+    pub seq_gaps: u64,
+    pub seq_missing: u64,
+    pub seq_dupes: u64,
+    pub seq_reorders: u64,
+
+    pub queue_drops: u64,
+    pub bars: u64,
+    pub last_bar: Bar,
+    /// Positions closed by stop-loss, take-profit, trailing stop or max hold
+    pub exits: u64,
+    pub risk_rejections: u64,
+}
+
+impl Default for SymbolMetrics {
+    fn default() -> Self {
+        Self {
+            trades: 0,
+            decisions: 0,
+            fills: 0,
+            pnl: 0.0,
+            realized_pnl: 0.0,
+            unrealized_pnl: 0.0,
+            position: 0.0,
+            avg_entry: 0.0,
+            perf: Stats::default(),
+            fees: 0.0,
+            slippage: 0.0,
+            last_price: 0.0,
+            lat_hist: Histogram::<u64>::new(3).unwrap(),
+            seq_gaps: 0,
+            seq_missing: 0,
+            seq_dupes: 0,
+            seq_reorders: 0,
+            queue_drops: 0,
+            bars: 0,
+            last_bar: Bar::default(),
+            exits: 0,
+            risk_rejections: 0,
+        }
+    }
+}
+
+/// (name, help, type, value) of a per-symbol series
+type Series = (&'static str, &'static str, &'static str, fn(&SymbolMetrics) -> f64);
+
+const SYMBOL_SERIES: &[Series] = &[
+    ("quant_trades_total", "Number of trades processed", "counter", |s| s.trades as f64),
+    ("quant_decisions_total", "Decisions made by strategy", "counter", |s| s.decisions as f64),
+    ("quant_fills_total", "Paper fills", "counter", |s| s.fills as f64),
+    ("quant_pnl", "Net PnL: realized + unrealized - fees, marked on every trade", "gauge", |s| s.pnl),
+    ("quant_realized_pnl", "Gross PnL of closed quantity", "gauge", |s| s.realized_pnl),
+    ("quant_unrealized_pnl", "Open position marked at the last price", "gauge", |s| s.unrealized_pnl),
+    ("quant_position", "Signed open quantity, negative while short", "gauge", |s| s.position),
+    ("quant_avg_entry_price", "Average entry price of the open quantity, 0 when flat", "gauge", |s| s.avg_entry),
+    ("quant_max_drawdown", "Largest peak-to-trough equity drop", "gauge", |s| s.perf.max_drawdown),
+    ("quant_max_drawdown_seconds", "Longest time below a previous equity peak", "gauge", |s| s.perf.max_drawdown_secs),
+    ("quant_drawdown", "Current distance below the equity peak", "gauge", |s| s.perf.drawdown),
+    ("quant_sharpe", "Annualized Sharpe ratio of interval PnL", "gauge", |s| s.perf.sharpe),
+    ("quant_sortino", "Annualized Sortino ratio of interval PnL", "gauge", |s| s.perf.sortino),
+    ("quant_win_rate", "Fraction of round trips with positive net PnL", "gauge", |s| s.perf.win_rate),
+    ("quant_avg_win", "Average net PnL of winning round trips", "gauge", |s| s.perf.avg_win),
+    ("quant_avg_loss", "Average net PnL of losing round trips", "gauge", |s| s.perf.avg_loss),
+    ("quant_profit_factor", "Gross wins over gross losses", "gauge", |s| s.perf.profit_factor),
+    ("quant_turnover", "Traded notional, quote currency", "gauge", |s| s.perf.turnover),
+    ("quant_exposure_ratio", "Fraction of time holding a position", "gauge", |s| s.perf.exposure),
+    ("quant_fees_total", "Fees paid on paper fills, quote currency", "counter", |s| s.fees),
+    ("quant_slippage_total", "Slippage cost on paper fills, quote currency", "counter", |s| s.slippage),
+    ("quant_last_price", "Last trade price", "gauge", |s| s.last_price),
+    ("quant_seq_gaps_total", "aggTrade id gaps detected", "counter", |s| s.seq_gaps as f64),
+    ("quant_seq_missing_total", "aggTrade ids skipped across all gaps", "counter", |s| s.seq_missing as f64),
+    ("quant_seq_dupes_total", "Duplicate aggTrade ids received", "counter", |s| s.seq_dupes as f64),
+    ("quant_seq_reorders_total", "aggTrade ids received after a higher id", "counter", |s| s.seq_reorders as f64),
+    ("quant_bars_total", "Bars completed by the bar builder", "counter", |s| s.bars as f64),
+    ("quant_bar_open", "Open of the last completed bar", "gauge", |s| s.last_bar.open),
+    ("quant_bar_high", "High of the last completed bar", "gauge", |s| s.last_bar.high),
+    ("quant_bar_low", "Low of the last completed bar", "gauge", |s| s.last_bar.low),
+    ("quant_bar_close", "Close of the last completed bar", "gauge", |s| s.last_bar.close),
+    ("quant_bar_volume", "Base volume of the last completed bar", "gauge", |s| s.last_bar.volume),
+    ("quant_bar_buy_volume", "Taker-buy base volume of the last completed bar", "gauge", |s| s.last_bar.buy_volume),
+    ("quant_bar_sell_volume", "Taker-sell base volume of the last completed bar", "gauge", |s| s.last_bar.sell_volume),
+    ("quant_bar_notional", "Quote notional of the last completed bar", "gauge", |s| s.last_bar.notional),
+    ("quant_bar_trades", "Trades in the last completed bar", "gauge", |s| s.last_bar.trades as f64),
+    ("quant_risk_rejections_total", "Order intents rejected by pre-trade risk checks", "counter", |s| {
+        s.risk_rejections as f64
+    }),
+    ("quant_protective_exits_total", "Positions closed by a stop, take-profit or max hold", "counter", |s| s.exits as f64),
+    (
+        "quant_queue_drops_total",
+        "Trades dropped or conflated because the strategy fell behind",
+        "counter",
+        |s| s.queue_drops as f64,
+    ),
+];
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Value of the `strategy` label on every per-symbol series
+    pub strategy: String,
+    // keyed by `AggTrade.s`; BTreeMap keeps scrape output stable
+    symbols: BTreeMap<String, SymbolMetrics>,
+
+    pub ws_connected: bool,
+    pub ws_reconnects: u64,
+    pub queue_depth: usize,
+    pub feed_stale: bool,
+    /// `TradingState` as 0 running, 1 paused, 2 killed
+    pub trading_state: u8,
+    pub feed_latency_ms: u64,
+
+    pub recorded_frames: u64,
+    pub record_errors: u64,
+}
+
+impl Metrics {
+    pub fn symbol(&mut self, symbol: &str) -> &mut SymbolMetrics {
+        if !self.symbols.contains_key(symbol) {
+            self.symbols.insert(symbol.to_string(), SymbolMetrics::default());
+        }
+        self.symbols.get_mut(symbol).unwrap()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let labels = |sym: &str| format!("symbol=\"{}\",strategy=\"{}\"", sym, self.strategy);
+
+        for (name, help, kind, value) in SYMBOL_SERIES {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+            for (sym, s) in &self.symbols {
+                let _ = writeln!(out, "{name}{{{}}} {}", labels(sym), value(s));
+            }
+        }
+
+        let _ = writeln!(out, "# HELP quant_latency_ms Decision latency histogram (p50/p90/p99)");
+        let _ = writeln!(out, "# TYPE quant_latency_ms summary");
+        for (sym, s) in &self.symbols {
+            for (q, label) in [(0.50, "0.50"), (0.90, "0.90"), (0.99, "0.99")] {
+                let v = s.lat_hist.value_at_quantile(q);
+                let _ = writeln!(out, "quant_latency_ms{{{},quantile=\"{label}\"}} {v}", labels(sym));
+            }
+        }
+
+        let _ = write!(
+            out,
+            concat!(
+                "# HELP quant_ws_connected WebSocket connection state (1 = connected)\n",
+                "# TYPE quant_ws_connected gauge\n",
+                "quant_ws_connected {}\n",
+                "# HELP quant_ws_reconnects_total WebSocket reconnect attempts\n",
+                "# TYPE quant_ws_reconnects_total counter\n",
+                "quant_ws_reconnects_total {}\n",
+                "# HELP quant_queue_depth Trades waiting in the reader -> strategy queue\n",
+                "# TYPE quant_queue_depth gauge\n",
+                "quant_queue_depth {}\n",
+                "# HELP quant_feed_stale Market data is stale and new entries are halted (1 = stale)\n",
+                "# TYPE quant_feed_stale gauge\n",
+                "quant_feed_stale {}\n",
+                "# HELP quant_feed_latency_ms Clock minus event time E of the last trade\n",
+                "# TYPE quant_feed_latency_ms gauge\n",
+                "quant_feed_latency_ms {}\n",
+                "# HELP quant_trading_state Operator trading state (0 = running, 1 = paused, 2 = killed)\n",
+                "# TYPE quant_trading_state gauge\n",
+                "quant_trading_state {}\n",
+                "# HELP quant_recorded_frames_total Raw WS frames written to disk\n",
+                "# TYPE quant_recorded_frames_total counter\n",
+                "quant_recorded_frames_total {}\n",
+                "# HELP quant_record_errors_total Recorder open/write failures\n",
+                "# TYPE quant_record_errors_total counter\n",
+                "quant_record_errors_total {}\n",
+            ),
+            self.ws_connected as u8,
+            self.ws_reconnects,
+            self.queue_depth,
+            self.feed_stale as u8,
+            self.feed_latency_ms,
+            self.trading_state,
+            self.recorded_frames,
+            self.record_errors
+        );
+        out
+    }
+}
+
+pub async fn metrics_handler() -> String {
+    METRICS.lock().unwrap().render()
+}
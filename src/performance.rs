@@ -0,0 +1,188 @@
+//! Equity curve and performance statistics for one symbol, updated on every
+//! trade so live, replay and backtest report the same numbers. Returns are
+//! PnL changes over fixed event-time intervals; Sharpe and Sortino are
+//! annualized over a 365-day year since crypto trades around the clock.
+
+use crate::indicators::KahanSum;
+use serde::{Deserialize, Deserializer, Serialize};
+
+const MS_PER_YEAR: f64 = 365.0 * 86_400_000.0;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub max_drawdown: f64,
+    /// Longest time spent below a previous equity peak
+    pub max_drawdown_secs: f64,
+    /// Current distance below the equity peak
+    pub drawdown: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub wins: u64,
+    pub losses: u64,
+    pub win_rate: f64,
+    pub avg_win: f64,
+    /// Negative
+    pub avg_loss: f64,
+    /// Gross wins / gross losses; infinite with no losing round trip
+    #[serde(deserialize_with = "null_as_infinity")]
+    pub profit_factor: f64,
+    /// Traded notional, quote currency
+    pub turnover: f64,
+    /// Fraction of the observed time spent holding a position
+    pub exposure: f64,
+}
+
+// JSON has no infinity; serde_json writes it as null
+fn null_as_infinity<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(d)?.unwrap_or(f64::INFINITY))
+}
+
+/// Serializable so snapshots can carry the curve and accumulators across restarts.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Performance {
+    interval_ms: u64,
+    /// (interval end, equity) samples
+    pub curve: Vec<(u64, f64)>,
+    next_sample_ms: u64,
+    last_sample: f64,
+    last_equity: f64,
+    returns: KahanSum,
+    returns_sq: KahanSum,
+    downside_sq: KahanSum,
+    n_returns: u64,
+    peak: f64,
+    peak_ts: u64,
+    first_ts: Option<u64>,
+    last_ts: u64,
+    in_market_ms: u64,
+    holding: bool,
+    gross_win: f64,
+    gross_loss: f64,
+    stats: Stats,
+}
+
+impl Performance {
+    pub fn new(interval_secs: u64) -> Self {
+        Self {
+            interval_ms: (interval_secs * 1000).max(1),
+            curve: Vec::new(),
+            next_sample_ms: 0,
+            last_sample: 0.0,
+            last_equity: 0.0,
+            returns: KahanSum::default(),
+            returns_sq: KahanSum::default(),
+            downside_sq: KahanSum::default(),
+            n_returns: 0,
+            peak: 0.0,
+            peak_ts: 0,
+            first_ts: None,
+            last_ts: 0,
+            in_market_ms: 0,
+            holding: false,
+            gross_win: 0.0,
+            gross_loss: 0.0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Mark equity at event time `ts_ms`; call after the trade's fills.
+    pub fn observe(&mut self, ts_ms: u64, equity: f64, holding: bool) {
+        let first = *self.first_ts.get_or_insert(ts_ms);
+        if ts_ms == first && self.curve.is_empty() {
+            self.peak_ts = ts_ms;
+            self.curve.push((ts_ms, 0.0));
+            self.next_sample_ms = ts_ms - ts_ms % self.interval_ms + self.interval_ms;
+        } else if self.holding {
+            self.in_market_ms += ts_ms.saturating_sub(self.last_ts);
+        }
+        self.last_ts = self.last_ts.max(ts_ms);
+        self.holding = holding;
+
+        // close every interval that ended before this trade at the equity it
+        // ended with; quiet intervals count as zero returns
+        while ts_ms >= self.next_sample_ms {
+            self.sample(self.next_sample_ms, self.last_equity);
+            self.next_sample_ms += self.interval_ms;
+        }
+        self.last_equity = equity;
+
+        if equity > self.peak {
+            self.peak = equity;
+            self.peak_ts = ts_ms;
+        }
+        let s = &mut self.stats;
+        s.drawdown = self.peak - equity;
+        s.max_drawdown = s.max_drawdown.max(s.drawdown);
+        if s.drawdown > 0.0 {
+            s.max_drawdown_secs = s.max_drawdown_secs.max(ts_ms.saturating_sub(self.peak_ts) as f64 / 1000.0);
+        }
+        let span = self.last_ts - first;
+        s.exposure = if span > 0 { self.in_market_ms as f64 / span as f64 } else { 0.0 };
+    }
+
+    fn sample(&mut self, ts_ms: u64, equity: f64) {
+        let r = equity - self.last_sample;
+        self.last_sample = equity;
+        self.curve.push((ts_ms, equity));
+        self.returns.add(r);
+        self.returns_sq.add(r * r);
+        if r < 0.0 {
+            self.downside_sq.add(r * r);
+        }
+        self.n_returns += 1;
+
+        let n = self.n_returns as f64;
+        if self.n_returns < 2 {
+            return;
+        }
+        let mean = self.returns.value() / n;
+        let var = ((self.returns_sq.value() - n * mean * mean) / (n - 1.0)).max(0.0);
+        let downside = (self.downside_sq.value() / n).sqrt();
+        let annualize = (MS_PER_YEAR / self.interval_ms as f64).sqrt();
+        self.stats.sharpe = if var > 0.0 { mean / var.sqrt() * annualize } else { 0.0 };
+        self.stats.sortino = if downside > 0.0 { mean / downside * annualize } else { 0.0 };
+    }
+
+    /// Account a paper fill; `round_trip` is the net PnL of a trip it closed.
+    pub fn on_fill(&mut self, notional: f64, round_trip: Option<f64>) {
+        let s = &mut self.stats;
+        s.turnover += notional;
+        let Some(pnl) = round_trip else { return };
+        if pnl > 0.0 {
+            s.wins += 1;
+            self.gross_win += pnl;
+        } else {
+            s.losses += 1;
+            self.gross_loss -= pnl;
+        }
+        let trips = s.wins + s.losses;
+        s.win_rate = s.wins as f64 / trips as f64;
+        s.avg_win = if s.wins > 0 { self.gross_win / s.wins as f64 } else { 0.0 };
+        s.avg_loss = if s.losses > 0 { -self.gross_loss / s.losses as f64 } else { 0.0 };
+        s.profit_factor = if self.gross_loss > 0.0 { self.gross_win / self.gross_loss } else { f64::INFINITY };
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+
+/// One human-readable line per symbol for shutdown and backtest reports.
+pub fn format_stats(s: &Stats) -> String {
+    format!(
+        "max_dd={:.2} ({:.0}s) sharpe={:.2} sortino={:.2} win_rate={:.1}% ({}W/{}L) avg_win={:.2} avg_loss={:.2} \
+         profit_factor={:.2} turnover={:.2} exposure={:.1}%",
+        s.max_drawdown,
+        s.max_drawdown_secs,
+        s.sharpe,
+        s.sortino,
+        s.win_rate * 100.0,
+        s.wins,
+        s.losses,
+        s.avg_win,
+        s.avg_loss,
+        s.profit_factor,
+        s.turnover,
+        s.exposure * 100.0
+    )
+}
@@ -0,0 +1,168 @@
+//! Position accounting for one symbol: signed quantity at an average entry
+//! price, realized PnL from closing fills, unrealized PnL marked at the last
+//! price, and fees. Net PnL = realized + unrealized - fees, which matches
+//! plain cash accounting but says where each dollar came from.
+
+use crate::strategy::{OrderIntent, Side};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Position {
+    /// Signed: negative while short
+    pub qty: f64,
+    /// Average entry price of the open quantity; 0 when flat
+    pub avg_price: f64,
+    /// Gross PnL from closed quantity
+    pub realized: f64,
+    pub fees: f64,
+    /// Times an open position was closed, either to flat or by flipping sides
+    pub round_trips: u64,
+    // realized PnL minus fees since the position was last opened
+    trip_pnl: f64,
+}
+
+/// What one fill did to the position.
+#[derive(Debug, Clone, Copy)]
+pub struct Applied {
+    /// Gross PnL realized by the closing part of the fill
+    pub realized: f64,
+    /// Net PnL of the round trip this fill completed, if any
+    pub round_trip: Option<f64>,
+}
+
+impl Position {
+    pub fn apply(&mut self, side: Side, qty: f64, price: f64, fee: f64) -> Applied {
+        let signed = side.sign() * qty;
+        self.fees += fee;
+        let mut applied = Applied { realized: 0.0, round_trip: None };
+
+        if self.qty == 0.0 || self.qty.signum() == signed.signum() {
+            // opening or adding: blend the entry price
+            let open = self.qty.abs();
+            self.avg_price = (self.avg_price * open + price * qty) / (open + qty);
+            self.qty += signed;
+            self.trip_pnl -= fee;
+            return applied;
+        }
+
+        let closed = qty.min(self.qty.abs());
+        applied.realized = (price - self.avg_price) * closed * self.qty.signum();
+        self.realized += applied.realized;
+        // the fee of a flipping fill is split between the closed and new trip
+        let close_fee = fee * closed / qty;
+        self.trip_pnl += applied.realized - close_fee;
+        self.qty += signed;
+
+        if self.qty == 0.0 || closed < qty {
+            self.round_trips += 1;
+            applied.round_trip = Some(self.trip_pnl);
+            self.trip_pnl = -(fee - close_fee);
+            self.avg_price = if self.qty == 0.0 { 0.0 } else { price };
+        }
+        applied
+    }
+
+    /// Order that flattens the position, if one is open.
+    pub fn closing_order(&self, price: f64, reason: String) -> Option<OrderIntent> {
+        if self.qty == 0.0 {
+            return None;
+        }
+        let side = if self.qty > 0.0 { Side::Sell } else { Side::Buy };
+        Some(OrderIntent { side, qty: self.qty.abs(), price, reason })
+    }
+
+    pub fn unrealized(&self, mark: f64) -> f64 {
+        if self.qty == 0.0 {
+            0.0
+        } else {
+            (mark - self.avg_price) * self.qty
+        }
+    }
+
+    /// Realized + unrealized at `mark`, net of fees
+    pub fn net_pnl(&self, mark: f64) -> f64 {
+        self.realized + self.unrealized(mark) - self.fees
+    }
+}
+
+/// One paper fill with the position it left behind.
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerEntry {
+    pub ts_ms: u64,
+    pub symbol: String,
+    pub side: &'static str,
+    pub qty: f64,
+    pub price: f64,
+    pub fee: f64,
+    pub reason: String,
+    pub pos_after: f64,
+    pub avg_price_after: f64,
+    pub realized: f64,
+    /// Net PnL of the round trip this fill closed
+    pub round_trip_pnl: Option<f64>,
+}
+
+/// Write the ledger as CSV, or JSON lines if `path` ends in `.json`/`.ndjson`.
+pub fn write_ledger(path: &Path, ledger: &[LedgerEntry]) -> anyhow::Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let json = path.extension().is_some_and(|e| e == "json" || e == "ndjson");
+    if json {
+        for e in ledger {
+            serde_json::to_writer(&mut out, e)?;
+            writeln!(out)?;
+        }
+    } else {
+        writeln!(out, "ts_ms,symbol,side,qty,price,fee,reason,pos_after,avg_price_after,realized,round_trip_pnl")?;
+        for e in ledger {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},\"{}\",{},{},{},{}",
+                e.ts_ms,
+                e.symbol,
+                e.side,
+                e.qty,
+                e.price,
+                e.fee,
+                e.reason.replace('"', "\"\""),
+                e.pos_after,
+                e.avg_price_after,
+                e.realized,
+                e.round_trip_pnl.map(|p| p.to_string()).unwrap_or_default()
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn flip_splits_fee_between_round_trips() {
+        let mut pos = Position::default();
+        pos.apply(Side::Buy, 1.0, 100.0, 1.0);
+
+        // closes the long (1 of 3) and opens a 2 lot short; fee 3 splits 1 / 2
+        let flip = pos.apply(Side::Sell, 3.0, 110.0, 3.0);
+        assert!(close(flip.realized, 10.0));
+        assert!(close(flip.round_trip.unwrap(), 10.0 - 1.0 - 1.0));
+        assert!(close(pos.qty, -2.0));
+        assert!(close(pos.avg_price, 110.0));
+
+        let cover = pos.apply(Side::Buy, 2.0, 105.0, 2.0);
+        assert!(close(cover.realized, 10.0));
+        assert!(close(cover.round_trip.unwrap(), 10.0 - 2.0 - 2.0));
+        assert_eq!(pos.round_trips, 2);
+        assert_eq!((pos.qty, pos.avg_price), (0.0, 0.0));
+        // round trips add up to the net PnL
+        assert!(close(pos.net_pnl(105.0), 8.0 + 6.0));
+    }
+}
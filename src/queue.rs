@@ -0,0 +1,175 @@
+//! Bounded reader -> strategy trade queue with a configurable overflow policy.
+//! tokio's mpsc can only reject the newest item, so this is a small
+//! Mutex<VecDeque> + Notify channel that can also evict or conflate.
+
+use crate::metrics::METRICS;
+use crate::AggTrade;
+use clap::ValueEnum;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backpressure {
+    /// Discard the incoming trade when the queue is full
+    DropNewest,
+    /// Evict the oldest queued trade to make room
+    DropOldest,
+    /// Wait for room; stalls the WS read loop (and eventually the socket)
+    Block,
+    /// Overwrite the newest queued trade of the same symbol, keeping the latest price
+    Conflate,
+}
+
+#[derive(Debug)]
+pub struct Closed;
+
+struct State {
+    buf: VecDeque<AggTrade>,
+    senders: usize,
+    rx_alive: bool,
+    // drops since the queue last had room; used to log start/end of a drop burst
+    burst_drops: u64,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    policy: Backpressure,
+    readable: Notify,
+    writable: Notify,
+}
+
+pub struct Sender {
+    shared: Arc<Shared>,
+}
+
+pub struct Receiver {
+    shared: Arc<Shared>,
+}
+
+pub fn channel(capacity: usize, policy: Backpressure) -> (Sender, Receiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buf: VecDeque::with_capacity(capacity),
+            senders: 1,
+            rx_alive: true,
+            burst_drops: 0,
+        }),
+        capacity: capacity.max(1),
+        policy,
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+    (Sender { shared: shared.clone() }, Receiver { shared })
+}
+
+impl Sender {
+    /// Enqueue a trade according to the policy. Only `Block` ever waits.
+    pub async fn send(&self, t: AggTrade) -> Result<(), Closed> {
+        let sh = &self.shared;
+        loop {
+            // register interest before checking so a pop in between is not missed
+            let writable = sh.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+            {
+                let mut st = sh.state.lock().unwrap();
+                if !st.rx_alive {
+                    return Err(Closed);
+                }
+                if st.buf.len() < sh.capacity {
+                    if st.burst_drops > 0 {
+                        eprintln!("trade queue has room again, {} trades dropped in last burst", st.burst_drops);
+                        st.burst_drops = 0;
+                    }
+                    st.buf.push_back(t);
+                    drop(st);
+                    sh.readable.notify_one();
+                    return Ok(());
+                }
+                if sh.policy != Backpressure::Block {
+                    // the trade that gets discarded; evictions can hit another symbol
+                    let dropped = match sh.policy {
+                        Backpressure::DropOldest => {
+                            st.buf.push_back(t);
+                            st.buf.pop_front()
+                        }
+                        Backpressure::Conflate => match st.buf.iter().rposition(|q| q.s == t.s) {
+                            Some(i) => Some(std::mem::replace(&mut st.buf[i], t)),
+                            None => {
+                                st.buf.push_back(t);
+                                st.buf.pop_front()
+                            }
+                        },
+                        // DropNewest: `t` is discarded
+                        _ => Some(t),
+                    };
+                    if st.burst_drops == 0 {
+                        eprintln!("trade queue full ({}), dropping trades ({:?})", sh.capacity, sh.policy);
+                    }
+                    st.burst_drops += 1;
+                    drop(st);
+                    if let Some(d) = dropped {
+                        METRICS.lock().unwrap().symbol(&d.s).queue_drops += 1;
+                    }
+                    return Ok(());
+                }
+            }
+            writable.await;
+        }
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut st = self.shared.state.lock().unwrap();
+        st.senders -= 1;
+        if st.senders == 0 {
+            drop(st);
+            self.shared.readable.notify_waiters();
+        }
+    }
+}
+
+impl Receiver {
+    /// Next trade, or None once every sender is gone and the queue is drained.
+    pub async fn recv(&mut self) -> Option<AggTrade> {
+        let sh = &self.shared;
+        loop {
+            let readable = sh.readable.notified();
+            tokio::pin!(readable);
+            readable.as_mut().enable();
+            {
+                let mut st = sh.state.lock().unwrap();
+                if let Some(t) = st.buf.pop_front() {
+                    drop(st);
+                    sh.writable.notify_one();
+                    return Some(t);
+                }
+                if st.senders == 0 {
+                    return None;
+                }
+            }
+            readable.await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.state.lock().unwrap().buf.len()
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().rx_alive = false;
+        self.shared.writable.notify_waiters();
+    }
+}
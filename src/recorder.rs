@@ -0,0 +1,174 @@
+//! Raw market-data capture. Every WS text frame is written verbatim with its
+//! local receive time as one JSON object per line:
+//!
+//!   {"ts":1724900000123,"frame":"{\"e\":\"aggTrade\",...}"}
+//!
+//! Files rotate by size and age and can be gzipped. Writing happens on a
+//! dedicated thread so disk stalls never block the WS reader.
+
+use crate::metrics::METRICS;
+use clap::Args as ClapArgs;
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct RecordOpts {
+    /// Directory for recorded files (created if missing)
+    #[arg(long, default_value = "data")]
+    pub out_dir: PathBuf,
+
+    /// Start a new file once the current one holds this many MiB (uncompressed)
+    #[arg(long, default_value_t = 256)]
+    pub rotate_mb: u64,
+
+    /// Start a new file after this many seconds
+    #[arg(long, default_value_t = 3600)]
+    pub rotate_secs: u64,
+
+    /// Gzip recorded files (.ndjson.gz)
+    #[arg(long, default_value_t = false)]
+    pub compress: bool,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// local receive time, ms since epoch
+    ts: u64,
+    frame: &'a str,
+}
+
+/// Cheap, cloneable handle the reader uses to hand frames to the writer thread.
+/// The file is finalized once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    tx: mpsc::Sender<(u64, String)>,
+}
+
+impl Recorder {
+    pub fn record(&self, frame: &str) {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let _ = self.tx.send((ts, frame.to_string()));
+    }
+}
+
+/// Start the writer thread. `prefix` names the files, e.g. `btcusdt_ethusdt`.
+pub fn spawn(opts: RecordOpts, prefix: String) -> io::Result<(Recorder, JoinHandle<()>)> {
+    std::fs::create_dir_all(&opts.out_dir)?;
+    let (tx, rx) = mpsc::channel();
+    let handle = std::thread::Builder::new()
+        .name("recorder".into())
+        .spawn(move || writer_loop(opts, prefix, rx))?;
+    Ok((Recorder { tx }, handle))
+}
+
+enum Sink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+struct Segment {
+    sink: Sink,
+    path: PathBuf,
+    bytes: u64,
+    opened: Instant,
+}
+
+impl Segment {
+    fn open(dir: &Path, prefix: &str, compress: bool) -> io::Result<Self> {
+        let start_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+        let ext = if compress { "ndjson.gz" } else { "ndjson" };
+        let path = dir.join(format!("{prefix}-{start_ms}.{ext}"));
+        let file = BufWriter::new(File::create(&path)?);
+        let sink = if compress {
+            Sink::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            Sink::Plain(file)
+        };
+        println!("Recording to {}", path.display());
+        Ok(Self { sink, path, bytes: 0, opened: Instant::now() })
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match &mut self.sink {
+            Sink::Plain(w) => w,
+            Sink::Gzip(w) => w,
+        }
+    }
+
+    fn write_line(&mut self, ts: u64, frame: &str) -> io::Result<()> {
+        let mut buf = serde_json::to_vec(&Line { ts, frame })?;
+        buf.push(b'\n');
+        self.writer().write_all(&buf)?;
+        self.bytes += buf.len() as u64;
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self.sink {
+            Sink::Plain(mut w) => w.flush(),
+            Sink::Gzip(w) => w.finish()?.flush(),
+        }
+    }
+}
+
+fn writer_loop(opts: RecordOpts, prefix: String, rx: mpsc::Receiver<(u64, String)>) {
+    let rotate_bytes = opts.rotate_mb.saturating_mul(1024 * 1024);
+    let rotate_after = Duration::from_secs(opts.rotate_secs);
+    let mut seg: Option<Segment> = None;
+
+    loop {
+        let (ts, frame) = match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(item) => item,
+            Err(RecvTimeoutError::Timeout) => {
+                // quiet market: make what we have durable
+                if let Some(s) = seg.as_mut() {
+                    if let Err(e) = s.writer().flush() {
+                        eprintln!("recorder: flush {} failed: {e}", s.path.display());
+                    }
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if seg.as_ref().is_some_and(|s| s.bytes >= rotate_bytes || s.opened.elapsed() >= rotate_after) {
+            close(seg.take());
+        }
+        if seg.is_none() {
+            match Segment::open(&opts.out_dir, &prefix, opts.compress) {
+                Ok(s) => seg = Some(s),
+                Err(e) => {
+                    eprintln!("recorder: cannot open file in {}: {e}", opts.out_dir.display());
+                    METRICS.lock().unwrap().record_errors += 1;
+                    continue;
+                }
+            }
+        }
+        let s = seg.as_mut().unwrap();
+        match s.write_line(ts, &frame) {
+            Ok(()) => METRICS.lock().unwrap().recorded_frames += 1,
+            Err(e) => {
+                eprintln!("recorder: write to {} failed: {e}", s.path.display());
+                METRICS.lock().unwrap().record_errors += 1;
+                // start over in a fresh file on the next frame
+                close(seg.take());
+            }
+        }
+    }
+    close(seg);
+}
+
+fn close(seg: Option<Segment>) {
+    if let Some(s) = seg {
+        let path = s.path.clone();
+        if let Err(e) = s.finish() {
+            eprintln!("recorder: closing {} failed: {e}", path.display());
+        }
+    }
+}
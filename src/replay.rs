@@ -0,0 +1,58 @@
+//! Replay historical market data into the trade queue so the strategy path is
+//! identical to live. See `history` for the accepted file formats.
+
+use crate::{history, queue};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct ReplayStats {
+    pub files: usize,
+    pub trades: u64,
+    /// Lines that were not an aggTrade (other frames, blank or malformed lines)
+    pub skipped: u64,
+}
+
+/// Push every trade in `files` into `tx`, merged across symbols by trade
+/// time. `speed` <= 0 replays as fast as the strategy consumes; otherwise
+/// recorded gaps are reproduced divided by `speed`.
+/// Runs on a blocking thread; returns early if the strategy side hangs up.
+pub fn spawn(
+    files: Vec<PathBuf>,
+    symbol: Option<String>,
+    speed: f64,
+    tx: queue::Sender,
+) -> tokio::task::JoinHandle<anyhow::Result<ReplayStats>> {
+    let rt = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let mut stats = ReplayStats::default();
+        // (first data ts, wall clock at first event) anchors pacing
+        let mut anchor: Option<(u64, Instant)> = None;
+        for path in &files {
+            println!("Replaying {}", path.display());
+        }
+        stats.files = files.len();
+        history::for_each_merged(&files, symbol.as_deref(), |item| {
+            let Some((ts, trade)) = item else {
+                stats.skipped += 1;
+                return ControlFlow::Continue(());
+            };
+            if speed > 0.0 {
+                let (ts0, wall0) = *anchor.get_or_insert((ts, Instant::now()));
+                let offset = Duration::from_secs_f64(ts.saturating_sub(ts0) as f64 / 1000.0 / speed);
+                let wait = (wall0 + offset).saturating_duration_since(Instant::now());
+                if !wait.is_zero() {
+                    std::thread::sleep(wait);
+                }
+            }
+            // a send error means the strategy side hung up
+            if rt.block_on(tx.send(trade)).is_err() {
+                return ControlFlow::Break(());
+            }
+            stats.trades += 1;
+            ControlFlow::Continue(())
+        })?;
+        Ok(stats)
+    })
+}
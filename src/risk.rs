@@ -0,0 +1,184 @@
+//! Pre-trade risk checks between strategy intents and paper fills.
+//! `--max-position` applies per symbol; notional, daily loss and order rate
+//! are account limits summed over every symbol's book. Orders that only
+//! reduce the position skip every check, so stops, flatten and kill can
+//! always get out.
+
+use crate::strategy::OrderIntent;
+use clap::Args as ClapArgs;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+const DAY_MS: u64 = 86_400_000;
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct RiskOpts {
+    /// Reject orders that would leave more than this absolute base quantity open in one symbol
+    #[arg(long)]
+    pub max_position: Option<f64>,
+
+    /// Reject orders that would leave more than this quote notional open across all symbols
+    #[arg(long)]
+    pub max_notional: Option<f64>,
+
+    /// Stop opening positions for the rest of the UTC day once the account's net PnL since midnight falls this far
+    #[arg(long)]
+    pub max_daily_loss: Option<f64>,
+
+    /// Reject orders beyond this many per rolling minute across all symbols
+    #[arg(long)]
+    pub max_orders_per_min: Option<usize>,
+
+    /// Reject entries whose decision price is more than this many bps from the last trade (fat-finger band)
+    #[arg(long)]
+    pub price_band_bps: Option<f64>,
+}
+
+/// Daily-loss baseline, kept across restarts so a redeploy doesn't reset it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RiskState {
+    pub day: u64,
+    pub day_start_equity: f64,
+}
+
+/// Why an order was rejected. `limit` names the check, for grouping log lines.
+#[derive(Debug)]
+pub struct Rejection {
+    pub limit: &'static str,
+    detail: String,
+}
+
+impl Rejection {
+    fn new(limit: &'static str, detail: String) -> Self {
+        Self { limit, detail }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.detail)
+    }
+}
+
+/// Account-wide side of the limits, shared by every book: each symbol's open
+/// notional and day PnL as of its last mark, and the order-rate window.
+#[derive(Default)]
+pub struct Account {
+    marks: BTreeMap<String, (f64, f64)>,
+    // accepted order times within the last minute, all symbols
+    orders: VecDeque<u64>,
+}
+
+impl Account {
+    /// (open notional, day PnL) summed over every symbol but `symbol`
+    fn others(&self, symbol: &str) -> (f64, f64) {
+        self.marks
+            .iter()
+            .filter(|(s, _)| s.as_str() != symbol)
+            .fold((0.0, 0.0), |(n, p), (_, (notional, pnl))| (n + notional, p + pnl))
+    }
+}
+
+pub struct Risk {
+    opts: RiskOpts,
+    symbol: String,
+    day: u64,
+    day_start_equity: f64,
+    equity: f64,
+    /// Set while new entries are halted, e.g. on a stale feed
+    pub halt: Option<String>,
+}
+
+impl Risk {
+    pub fn new(opts: &RiskOpts, symbol: &str) -> Self {
+        Self {
+            opts: opts.clone(),
+            symbol: symbol.to_string(),
+            day: 0,
+            day_start_equity: 0.0,
+            equity: 0.0,
+            halt: None,
+        }
+    }
+
+    pub fn state(&self) -> RiskState {
+        RiskState { day: self.day, day_start_equity: self.day_start_equity }
+    }
+
+    pub fn restore(&mut self, state: RiskState) {
+        self.day = state.day;
+        self.day_start_equity = state.day_start_equity;
+    }
+
+    /// Mark net PnL and open notional at event time, here and in `account`;
+    /// the first mark of a UTC day is its baseline.
+    pub fn mark(&mut self, ts_ms: u64, equity: f64, notional: f64, account: &mut Account) {
+        let day = ts_ms / DAY_MS;
+        if day != self.day {
+            self.day = day;
+            self.day_start_equity = equity;
+        }
+        self.equity = equity;
+        account.marks.insert(self.symbol.clone(), (notional, equity - self.day_start_equity));
+    }
+
+    /// Ok if `intent` may fill at `price` (last trade plus slippage) with the
+    /// position at `pos_qty`, otherwise the reason it was rejected.
+    pub fn check(
+        &self,
+        intent: &OrderIntent,
+        pos_qty: f64,
+        price: f64,
+        last_price: f64,
+        ts_ms: u64,
+        account: &mut Account,
+    ) -> Result<(), Rejection> {
+        let after = pos_qty + intent.side.sign() * intent.qty;
+        let reducing = after.abs() < pos_qty.abs() && (after == 0.0 || after.signum() == pos_qty.signum());
+        if reducing {
+            return Ok(());
+        }
+        if let Some(halt) = &self.halt {
+            return Err(Rejection::new("halt", format!("entries halted: {halt}")));
+        }
+        if let Some(band) = self.opts.price_band_bps {
+            let dev_bps = (intent.price / last_price - 1.0).abs() * 10000.0;
+            if !dev_bps.is_finite() || dev_bps > band {
+                let detail = format!("order price {:.2} is {dev_bps:.1} bps from last trade {last_price:.2}", intent.price);
+                return Err(Rejection::new("price_band", detail));
+            }
+        }
+        if let Some(max) = self.opts.max_position {
+            if after.abs() > max {
+                return Err(Rejection::new("max_position", format!("position {after:.6} exceeds max {max}")));
+            }
+        }
+        let (other_notional, other_day_pnl) = account.others(&self.symbol);
+        if let Some(max) = self.opts.max_notional {
+            let notional = other_notional + after.abs() * price;
+            if notional > max {
+                return Err(Rejection::new("max_notional", format!("account notional {notional:.2} exceeds max {max}")));
+            }
+        }
+        if let Some(max) = self.opts.max_daily_loss {
+            let day_pnl = other_day_pnl + self.equity - self.day_start_equity;
+            if day_pnl <= -max {
+                let detail = format!("account daily loss {:.2} reached max {max}", -day_pnl);
+                return Err(Rejection::new("max_daily_loss", detail));
+            }
+        }
+        if let Some(max) = self.opts.max_orders_per_min {
+            let orders = &mut account.orders;
+            while orders.front().is_some_and(|t| *t + 60_000 <= ts_ms) {
+                orders.pop_front();
+            }
+            if orders.len() >= max {
+                let detail = format!("{} orders in the last minute across all symbols, max {max}", orders.len());
+                return Err(Rejection::new("max_orders_per_min", detail));
+            }
+            orders.push_back(ts_ms);
+        }
+        Ok(())
+    }
+}
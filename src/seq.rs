@@ -0,0 +1,138 @@
+//! Per-symbol aggTrade id tracking. `AggTrade.a` increases by exactly one per
+//! aggregate trade, so any other step means the tape we trade on is incomplete.
+
+use crate::metrics::METRICS;
+use crate::AggTrade;
+use std::collections::{HashMap, VecDeque};
+
+/// Max unfilled gap ranges remembered per symbol for reorder detection
+const MAX_OPEN_GAPS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqEvent {
+    First,
+    InOrder,
+    /// `missing` ids were skipped right before this one
+    Gap { missing: u64 },
+    Duplicate,
+    /// Arrived after a higher id but fills a previously reported gap
+    Reordered,
+}
+
+#[derive(Default)]
+struct SymbolSeq {
+    last: Option<u64>,
+    // unfilled gaps as inclusive id ranges, oldest first
+    open_gaps: VecDeque<(u64, u64)>,
+}
+
+impl SymbolSeq {
+    fn observe(&mut self, id: u64) -> SeqEvent {
+        let Some(last) = self.last else {
+            self.last = Some(id);
+            return SeqEvent::First;
+        };
+        if id == last + 1 {
+            self.last = Some(id);
+            SeqEvent::InOrder
+        } else if id > last {
+            if self.open_gaps.len() == MAX_OPEN_GAPS {
+                self.open_gaps.pop_front();
+            }
+            self.open_gaps.push_back((last + 1, id - 1));
+            self.last = Some(id);
+            SeqEvent::Gap { missing: id - last - 1 }
+        } else if self.fill(id) {
+            SeqEvent::Reordered
+        } else {
+            SeqEvent::Duplicate
+        }
+    }
+
+    /// Remove `id` from the open gaps; false if it was never missing.
+    fn fill(&mut self, id: u64) -> bool {
+        let Some(i) = self.open_gaps.iter().position(|&(lo, hi)| lo <= id && id <= hi) else {
+            return false;
+        };
+        let (lo, hi) = self.open_gaps[i];
+        match (id == lo, id == hi) {
+            (true, true) => {
+                self.open_gaps.remove(i);
+            }
+            (true, false) => self.open_gaps[i].0 = id + 1,
+            (false, true) => self.open_gaps[i].1 = id - 1,
+            (false, false) => {
+                self.open_gaps[i].1 = id - 1;
+                self.open_gaps.insert(i + 1, (id + 1, hi));
+            }
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct SeqTracker {
+    by_symbol: HashMap<String, SymbolSeq>,
+}
+
+impl SeqTracker {
+    pub fn observe(&mut self, symbol: &str, id: u64) -> SeqEvent {
+        if let Some(seq) = self.by_symbol.get_mut(symbol) {
+            return seq.observe(id);
+        }
+        self.by_symbol.entry(symbol.to_string()).or_default().observe(id)
+    }
+
+    /// `observe` plus per-symbol metrics and a log line for gaps and reorders.
+    pub fn track(&mut self, tr: &AggTrade) -> SeqEvent {
+        let ev = self.observe(&tr.s, tr.a);
+        {
+            let mut g = METRICS.lock().unwrap();
+            let m = g.symbol(&tr.s);
+            match ev {
+                SeqEvent::Gap { missing } => {
+                    m.seq_gaps += 1;
+                    m.seq_missing += missing;
+                }
+                SeqEvent::Duplicate => m.seq_dupes += 1,
+                SeqEvent::Reordered => m.seq_reorders += 1,
+                SeqEvent::First | SeqEvent::InOrder => {}
+            }
+        }
+        match ev {
+            SeqEvent::Gap { missing } => {
+                eprintln!("[{}] aggTrade gap: {} ids missing before {}", tr.s, missing, tr.a)
+            }
+            SeqEvent::Reordered => eprintln!("[{}] aggTrade {} arrived out of order", tr.s, tr.a),
+            _ => {}
+        }
+        ev
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_splits_gap_range() {
+        let mut seq = SymbolSeq::default();
+        assert_eq!(seq.observe(10), SeqEvent::First);
+        assert_eq!(seq.observe(16), SeqEvent::Gap { missing: 5 });
+        assert_eq!(Vec::from(seq.open_gaps.clone()), [(11, 15)]);
+
+        // middle, then both ends, then the last id of a range
+        assert!(seq.fill(13));
+        assert_eq!(Vec::from(seq.open_gaps.clone()), [(11, 12), (14, 15)]);
+        assert!(seq.fill(11));
+        assert!(seq.fill(15));
+        assert_eq!(Vec::from(seq.open_gaps.clone()), [(12, 12), (14, 14)]);
+        assert!(seq.fill(12));
+        assert_eq!(Vec::from(seq.open_gaps.clone()), [(14, 14)]);
+
+        assert!(!seq.fill(13), "already filled");
+        assert_eq!(seq.observe(14), SeqEvent::Reordered);
+        assert_eq!(seq.observe(14), SeqEvent::Duplicate);
+        assert!(seq.open_gaps.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//! End-of-session summary for live and replay runs: printed on shutdown and
+//! optionally written as JSON for run scripts.
+
+use crate::engine::Engine;
+use crate::metrics::METRICS;
+use crate::performance::{self, Stats};
+use clap::Args as ClapArgs;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct SessionOpts {
+    /// Close open positions at the last price when the session ends
+    #[arg(long, default_value_t = false)]
+    pub flatten_on_exit: bool,
+
+    /// Write the end-of-session summary as JSON to this path
+    #[arg(long)]
+    pub summary: Option<PathBuf>,
+
+    /// Periodically save paper-trading state to this JSON file (and once more on exit)
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// Seconds between snapshots
+    #[arg(long, default_value_t = 30)]
+    pub snapshot_secs: u64,
+
+    /// Restore state from --snapshot at startup, if the file exists
+    #[arg(long, default_value_t = false, requires = "snapshot")]
+    pub resume: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SymbolSummary {
+    pub trades: u64,
+    pub fills: u64,
+    pub round_trips: u64,
+    pub pnl: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+    pub position: f64,
+    pub last_price: f64,
+    pub latency: LatencySummary,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    /// Why the session ended: "signal", "killed" or "feed_ended"
+    pub reason: &'static str,
+    pub ended_ms: u64,
+    pub trades: u64,
+    pub fills: u64,
+    pub pnl: f64,
+    pub fees: f64,
+    pub symbols: BTreeMap<String, SymbolSummary>,
+}
+
+impl Summary {
+    pub fn collect(engine: &Engine, reason: &'static str) -> Self {
+        let mut g = METRICS.lock().unwrap();
+        let mut symbols = BTreeMap::new();
+        for (sym, book) in engine.books() {
+            let m = g.symbol(sym);
+            let h = &m.lat_hist;
+            symbols.insert(
+                sym.clone(),
+                SymbolSummary {
+                    trades: m.trades,
+                    fills: book.fills,
+                    round_trips: book.position.round_trips,
+                    pnl: book.equity(),
+                    realized_pnl: book.position.realized,
+                    unrealized_pnl: book.position.unrealized(book.last_price),
+                    fees: book.position.fees,
+                    position: book.position.qty,
+                    last_price: book.last_price,
+                    latency: LatencySummary {
+                        p50_ms: h.value_at_quantile(0.50),
+                        p90_ms: h.value_at_quantile(0.90),
+                        p99_ms: h.value_at_quantile(0.99),
+                        max_ms: h.max(),
+                    },
+                    stats: book.perf.stats(),
+                },
+            );
+        }
+        Self {
+            reason,
+            ended_ms: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+            trades: symbols.values().map(|s| s.trades).sum(),
+            fills: symbols.values().map(|s| s.fills).sum(),
+            // fold from +0.0: an empty f64 sum is -0.0
+            pnl: symbols.values().fold(0.0, |acc, s| acc + s.pnl),
+            fees: symbols.values().fold(0.0, |acc, s| acc + s.fees),
+            symbols,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("=== Session summary ({}) ===", self.reason);
+        println!("trades={} fills={} pnl={:.2} fees={:.2}", self.trades, self.fills, self.pnl, self.fees);
+        for (sym, s) in &self.symbols {
+            println!(
+                "{sym}: trades={} fills={} round_trips={} pnl={:.2} (realized={:.2} unrealized={:.2}) position={} \
+                 latency p50={}ms p90={}ms p99={}ms",
+                s.trades,
+                s.fills,
+                s.round_trips,
+                s.pnl,
+                s.realized_pnl,
+                s.unrealized_pnl,
+                s.position,
+                s.latency.p50_ms,
+                s.latency.p90_ms,
+                s.latency.p99_ms
+            );
+            println!("{sym}: {}", performance::format_stats(&s.stats));
+        }
+    }
+}
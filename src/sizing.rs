@@ -0,0 +1,209 @@
+//! Position sizing: how much to trade when a strategy opens a side. Sizes are
+//! rounded down to the lot size and dropped below the exchange min-notional,
+//! so the same strategy works on btcusdt and dogeusdt.
+
+use crate::indicators::RollingStd;
+use crate::position::Position;
+use crate::strategy::Fill;
+use clap::{Args as ClapArgs, ValueEnum};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizingKind {
+    /// --qty base units per entry
+    FixedQty,
+    /// --notional quote currency per entry
+    Notional,
+    /// --equity-pct of current paper equity (--capital plus PnL) per entry
+    EquityPct,
+    /// Size so one sample's return std equals --vol-target-bps of equity, capped at 1x equity
+    VolTarget,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct SizingOpts {
+    /// Position sizing policy
+    #[arg(long, value_enum, default_value_t = SizingKind::FixedQty)]
+    pub sizing: SizingKind,
+
+    /// Base quantity for --sizing fixed-qty
+    #[arg(long, default_value_t = 1.0)]
+    pub qty: f64,
+
+    /// Quote notional for --sizing notional
+    #[arg(long, default_value_t = 1000.0)]
+    pub notional: f64,
+
+    /// Percent of equity for --sizing equity-pct
+    #[arg(long, default_value_t = 10.0)]
+    pub equity_pct: f64,
+
+    /// Starting paper equity in quote currency, for equity-pct and vol-target
+    #[arg(long, default_value_t = 10000.0)]
+    pub capital: f64,
+
+    /// Target per-sample (trade or bar) volatility in bps of equity for --sizing vol-target
+    #[arg(long, default_value_t = 5.0)]
+    pub vol_target_bps: f64,
+
+    /// Samples in the realized-volatility window for --sizing vol-target
+    #[arg(long, default_value_t = 100)]
+    pub vol_window: usize,
+
+    /// Quantity step; sizes are rounded down to a multiple (0 = no rounding).
+    /// Per symbol as `btcusdt=0.00001,dogeusdt=1`; a bare number covers the rest
+    #[arg(long, default_value = "0")]
+    pub lot_size: PerSymbol,
+
+    /// Entries whose notional would be below this are skipped; per symbol like --lot-size
+    #[arg(long, default_value = "0")]
+    pub min_notional: PerSymbol,
+}
+
+/// Exchange filter value that differs by symbol: `btcusdt=0.00001,dogeusdt=1`,
+/// optionally with a bare number for symbols not listed (default 0).
+#[derive(Debug, Clone, Default)]
+pub struct PerSymbol {
+    default: f64,
+    symbols: Vec<(String, f64)>,
+}
+
+impl PerSymbol {
+    pub fn get(&self, symbol: &str) -> f64 {
+        self.symbols
+            .iter()
+            .find(|(s, _)| s.eq_ignore_ascii_case(symbol))
+            .map_or(self.default, |(_, v)| *v)
+    }
+}
+
+impl FromStr for PerSymbol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = PerSymbol::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (symbol, value) = match part.split_once('=') {
+                Some((symbol, value)) => (Some(symbol.trim()), value.trim()),
+                None => (None, part),
+            };
+            let value: f64 = value.parse().map_err(|_| format!("invalid number {value:?} in {part:?}"))?;
+            if value < 0.0 {
+                return Err(format!("{part:?} must not be negative"));
+            }
+            match symbol {
+                Some("") => return Err(format!("missing symbol in {part:?}")),
+                Some(symbol) => out.symbols.push((symbol.to_uppercase(), value)),
+                None => out.default = value,
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Per-strategy sizer. Tracks its own paper equity from fills so it needs no
+/// access to the engine's books.
+pub struct Sizer {
+    opts: SizingOpts,
+    // this symbol's --lot-size / --min-notional
+    lot_size: f64,
+    min_notional: f64,
+    cash: f64,
+    pos_qty: f64,
+    last_price: Option<f64>,
+    // std of log returns between consecutive observed prices
+    vol: RollingStd,
+}
+
+impl Sizer {
+    pub fn new(opts: &SizingOpts, symbol: &str) -> Self {
+        Self {
+            opts: opts.clone(),
+            lot_size: opts.lot_size.get(symbol),
+            min_notional: opts.min_notional.get(symbol),
+            cash: 0.0,
+            pos_qty: 0.0,
+            last_price: None,
+            vol: RollingStd::new(opts.vol_window),
+        }
+    }
+
+    /// Feed every price the strategy sees, before asking for a size.
+    pub fn observe(&mut self, price: f64) {
+        if let Some(prev) = self.last_price.filter(|p| *p > 0.0 && price > 0.0) {
+            self.vol.update((price / prev).ln());
+        }
+        self.last_price = Some(price);
+    }
+
+    pub fn on_fill(&mut self, fill: &Fill) {
+        let signed = fill.side.sign() * fill.qty;
+        self.pos_qty += signed;
+        self.cash -= fill.price * signed + fill.fee;
+    }
+
+    /// Resume from a book's position: cash as if its fills had gone through here.
+    pub fn restore(&mut self, pos: &Position) {
+        self.pos_qty = pos.qty;
+        self.cash = pos.realized - pos.fees - pos.qty * pos.avg_price;
+    }
+
+    fn equity(&self, price: f64) -> f64 {
+        self.opts.capital + self.cash + self.pos_qty * price
+    }
+
+    /// Unsigned entry size at `price`, after lot and min-notional rounding;
+    /// 0 means don't enter.
+    pub fn qty(&self, price: f64) -> f64 {
+        if price <= 0.0 {
+            return 0.0;
+        }
+        let equity = self.equity(price).max(0.0);
+        let raw = match self.opts.sizing {
+            SizingKind::FixedQty => self.opts.qty,
+            SizingKind::Notional => self.opts.notional / price,
+            SizingKind::EquityPct => equity * self.opts.equity_pct / 100.0 / price,
+            SizingKind::VolTarget => match self.vol.current() {
+                Some((_, std)) if std > 0.0 => {
+                    let notional = equity * self.opts.vol_target_bps / 10000.0 / std;
+                    notional.min(equity) / price
+                }
+                _ => 0.0,
+            },
+        };
+        let qty = if self.lot_size > 0.0 {
+            // epsilon keeps 0.3 / 0.1 from rounding down to 2 lots
+            (raw / self.lot_size + 1e-9).floor() * self.lot_size
+        } else {
+            raw
+        };
+        if qty <= 0.0 || qty * price < self.min_notional {
+            return 0.0;
+        }
+        qty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_symbol_parses_overrides_and_default() {
+        let lot: PerSymbol = "btcusdt=0.00001, DOGEUSDT=1,0.1".parse().unwrap();
+        assert_eq!(lot.get("BTCUSDT"), 0.00001);
+        assert_eq!(lot.get("dogeusdt"), 1.0);
+        assert_eq!(lot.get("ETHUSDT"), 0.1);
+
+        let bare: PerSymbol = "5".parse().unwrap();
+        assert_eq!(bare.get("BTCUSDT"), 5.0);
+        assert_eq!("".parse::<PerSymbol>().unwrap().get("BTCUSDT"), 0.0);
+    }
+
+    #[test]
+    fn per_symbol_rejects_bad_values() {
+        assert!("btcusdt=x".parse::<PerSymbol>().is_err());
+        assert!("=1".parse::<PerSymbol>().is_err());
+        assert!("btcusdt=-1".parse::<PerSymbol>().is_err());
+    }
+}
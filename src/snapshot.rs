@@ -0,0 +1,128 @@
+//! Paper-trading state snapshots so a restart can pick up where the last
+//! process stopped. Accounting, performance, exit/risk state and metric
+//! counters are stored as-is; strategy indicators are rebuilt by replaying
+//! each symbol's most recent trades, or bars with `--bar`, into a fresh
+//! strategy, as many as its windows need. Latency histograms and the fill
+//! ledger start over.
+
+use crate::bars::{Bar, BarState};
+use crate::exits::ExitState;
+use crate::metrics::SymbolMetrics;
+use crate::performance::Performance;
+use crate::position::Position;
+use crate::risk::RiskState;
+use crate::strategy::Trade;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Bumped when the layout changes incompatibly.
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub saved_ms: u64,
+    /// `StrategyKind::name()` the state was built with
+    pub strategy: String,
+    pub books: BTreeMap<String, BookSnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub position: Position,
+    pub last_price: f64,
+    pub fills: u64,
+    pub slippage: f64,
+    pub perf: Performance,
+    pub exits: ExitState,
+    pub risk: RiskState,
+    pub counters: Counters,
+    /// Oldest first
+    pub recent: Vec<Trade>,
+    /// Closed bars when running with `--bar`, oldest first
+    pub recent_bars: Vec<Bar>,
+    /// Bar in progress
+    pub bar: Option<BarState>,
+}
+
+/// Monotonic per-symbol metric counters.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Counters {
+    pub trades: u64,
+    pub decisions: u64,
+    pub fills: u64,
+    pub fees: f64,
+    pub slippage: f64,
+    pub seq_gaps: u64,
+    pub seq_missing: u64,
+    pub seq_dupes: u64,
+    pub seq_reorders: u64,
+    pub queue_drops: u64,
+    pub bars: u64,
+    pub exits: u64,
+    pub risk_rejections: u64,
+}
+
+impl Counters {
+    pub fn from_metrics(m: &SymbolMetrics) -> Self {
+        Self {
+            trades: m.trades,
+            decisions: m.decisions,
+            fills: m.fills,
+            fees: m.fees,
+            slippage: m.slippage,
+            seq_gaps: m.seq_gaps,
+            seq_missing: m.seq_missing,
+            seq_dupes: m.seq_dupes,
+            seq_reorders: m.seq_reorders,
+            queue_drops: m.queue_drops,
+            bars: m.bars,
+            exits: m.exits,
+            risk_rejections: m.risk_rejections,
+        }
+    }
+
+    pub fn apply(&self, m: &mut SymbolMetrics) {
+        m.trades = self.trades;
+        m.decisions = self.decisions;
+        m.fills = self.fills;
+        m.fees = self.fees;
+        m.slippage = self.slippage;
+        m.seq_gaps = self.seq_gaps;
+        m.seq_missing = self.seq_missing;
+        m.seq_dupes = self.seq_dupes;
+        m.seq_reorders = self.seq_reorders;
+        m.queue_drops = self.queue_drops;
+        m.bars = self.bars;
+        m.exits = self.exits;
+        m.risk_rejections = self.risk_rejections;
+    }
+}
+
+/// Write via a temp file and rename, so a crash mid-write never leaves a
+/// truncated snapshot behind.
+pub fn save(path: &Path, snap: &Snapshot) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec(snap)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// None if there is no snapshot at `path` yet.
+pub fn load(path: &Path) -> anyhow::Result<Option<Snapshot>> {
+    let bytes = match std::fs::read(path) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow::anyhow!("{}: {e}", path.display())),
+    };
+    let snap: Snapshot =
+        serde_json::from_slice(&bytes).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+    anyhow::ensure!(
+        snap.version == VERSION,
+        "{}: snapshot version {} (expected {VERSION})",
+        path.display(),
+        snap.version
+    );
+    Ok(Some(snap))
+}
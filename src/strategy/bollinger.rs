@@ -0,0 +1,62 @@
+use super::{to_side, Fill, OrderIntent, Strategy, Trade};
+use crate::bars::Bar;
+use crate::indicators::RollingStd;
+use crate::position::Position;
+use crate::sizing::Sizer;
+
+/// Mean reversion: buy when price falls below mean - k * std, sell short when
+/// it rises above mean + k * std, and exit either side once price has
+/// reverted to the mean.
+pub struct Bollinger {
+    k: f64,
+    stats: RollingStd,
+    allow_short: bool,
+    sizer: Sizer,
+    pos_qty: f64,
+}
+
+impl Bollinger {
+    pub fn new(window: usize, k: f64, allow_short: bool, sizer: Sizer) -> Self {
+        Self { k, stats: RollingStd::new(window), allow_short, sizer, pos_qty: 0.0 }
+    }
+
+    fn signal(&mut self, price: f64) -> Vec<OrderIntent> {
+        self.sizer.observe(price);
+        let Some((mean, std)) = self.stats.update(price) else {
+            return Vec::new();
+        };
+        let lower = mean - self.k * std;
+        let upper = mean + self.k * std;
+
+        let dir = if price < lower {
+            1.0
+        } else if price > upper && self.allow_short {
+            -1.0
+        } else if (self.pos_qty > 0.0 && price >= mean) || (self.pos_qty < 0.0 && price <= mean) {
+            0.0
+        } else {
+            return Vec::new();
+        };
+        to_side(self.pos_qty, dir, &self.sizer, price, || format!("mean={mean:.2} std={std:.2}"))
+    }
+}
+
+impl Strategy for Bollinger {
+    fn on_trade(&mut self, trade: &Trade) -> Vec<OrderIntent> {
+        self.signal(trade.price)
+    }
+
+    fn on_bar(&mut self, bar: &Bar) -> Vec<OrderIntent> {
+        self.signal(bar.close)
+    }
+
+    fn on_fill(&mut self, fill: &Fill) {
+        self.pos_qty += fill.side.sign() * fill.qty;
+        self.sizer.on_fill(fill);
+    }
+
+    fn on_restore(&mut self, position: &Position) {
+        self.pos_qty = position.qty;
+        self.sizer.restore(position);
+    }
+}
@@ -0,0 +1,70 @@
+use super::{to_side, Fill, OrderIntent, Strategy, Trade};
+use crate::bars::Bar;
+use crate::indicators::MovingAverage;
+use crate::position::Position;
+use crate::sizing::Sizer;
+
+/// Long while the fast MA is above the slow MA, short (or flat) while below;
+/// acts only on the cross itself.
+pub struct Crossover {
+    fast: Box<dyn MovingAverage>,
+    slow: Box<dyn MovingAverage>,
+    allow_short: bool,
+    sizer: Sizer,
+    // sign of (fast - slow) at the previous update, once both are warm
+    prev_above: Option<bool>,
+    pos_qty: f64,
+}
+
+impl Crossover {
+    pub fn new(
+        fast: Box<dyn MovingAverage>,
+        slow: Box<dyn MovingAverage>,
+        allow_short: bool,
+        sizer: Sizer,
+    ) -> Self {
+        Self { fast, slow, allow_short, sizer, prev_above: None, pos_qty: 0.0 }
+    }
+
+    fn signal(&mut self, price: f64, qty: f64, ts_ms: u64) -> Vec<OrderIntent> {
+        self.sizer.observe(price);
+        let fast = self.fast.update(price, qty, ts_ms);
+        let slow = self.slow.update(price, qty, ts_ms);
+        let (Some(fast), Some(slow)) = (fast, slow) else {
+            return Vec::new();
+        };
+        let above = fast > slow;
+        let crossed = self.prev_above.is_some_and(|prev| prev != above);
+        self.prev_above = Some(above);
+        if !crossed {
+            return Vec::new();
+        }
+
+        let dir = match (above, self.allow_short) {
+            (true, _) => 1.0,
+            (false, true) => -1.0,
+            (false, false) => 0.0,
+        };
+        to_side(self.pos_qty, dir, &self.sizer, price, || format!("fast={fast:.2} slow={slow:.2}"))
+    }
+}
+
+impl Strategy for Crossover {
+    fn on_trade(&mut self, trade: &Trade) -> Vec<OrderIntent> {
+        self.signal(trade.price, trade.qty, trade.ts_ms)
+    }
+
+    fn on_bar(&mut self, bar: &Bar) -> Vec<OrderIntent> {
+        self.signal(bar.close, bar.volume, bar.close_ms)
+    }
+
+    fn on_fill(&mut self, fill: &Fill) {
+        self.pos_qty += fill.side.sign() * fill.qty;
+        self.sizer.on_fill(fill);
+    }
+
+    fn on_restore(&mut self, position: &Position) {
+        self.pos_qty = position.qty;
+        self.sizer.restore(position);
+    }
+}
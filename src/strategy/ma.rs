@@ -0,0 +1,62 @@
+use super::{to_side, Fill, OrderIntent, Strategy, Trade};
+use crate::bars::Bar;
+use crate::position::Position;
+use crate::indicators::MovingAverage;
+use crate::sizing::Sizer;
+
+/// Breakout around a moving average of trade prices: long above the upper
+/// threshold, short (or flat if shorting is off) below the lower one.
+pub struct MaThreshold {
+    threshold_bps: u32,
+    ma: Box<dyn MovingAverage>,
+    allow_short: bool,
+    sizer: Sizer,
+    pos_qty: f64,
+}
+
+impl MaThreshold {
+    pub fn new(ma: Box<dyn MovingAverage>, threshold_bps: u32, allow_short: bool, sizer: Sizer) -> Self {
+        Self { threshold_bps, ma, allow_short, sizer, pos_qty: 0.0 }
+    }
+}
+
+impl MaThreshold {
+    fn signal(&mut self, price: f64, qty: f64, ts_ms: u64) -> Vec<OrderIntent> {
+        self.sizer.observe(price);
+        let Some(ma) = self.ma.update(price, qty, ts_ms) else {
+            return Vec::new();
+        };
+        let up = ma * (1.0 + self.threshold_bps as f64 / 10000.0);
+        let dn = ma * (1.0 - self.threshold_bps as f64 / 10000.0);
+
+        let dir = if price > up {
+            1.0
+        } else if price < dn {
+            if self.allow_short { -1.0 } else { 0.0 }
+        } else {
+            return Vec::new();
+        };
+        to_side(self.pos_qty, dir, &self.sizer, price, || format!("ma={ma:.2}"))
+    }
+}
+
+impl Strategy for MaThreshold {
+    fn on_trade(&mut self, trade: &Trade) -> Vec<OrderIntent> {
+        self.signal(trade.price, trade.qty, trade.ts_ms)
+    }
+
+    /// Same rule on bar closes; the window then counts bars instead of trades.
+    fn on_bar(&mut self, bar: &Bar) -> Vec<OrderIntent> {
+        self.signal(bar.close, bar.volume, bar.close_ms)
+    }
+
+    fn on_fill(&mut self, fill: &Fill) {
+        self.pos_qty += fill.side.sign() * fill.qty;
+        self.sizer.on_fill(fill);
+    }
+
+    fn on_restore(&mut self, position: &Position) {
+        self.pos_qty = position.qty;
+        self.sizer.restore(position);
+    }
+}
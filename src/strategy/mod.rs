@@ -0,0 +1,168 @@
+//! Strategy interface. A strategy instance trades one symbol: it sees that
+//! symbol's trades, periodic timer ticks and its own fills, and answers with
+//! order intents that the engine executes against the paper trader.
+
+mod bollinger;
+mod crossover;
+mod ma;
+
+use crate::bars::Bar;
+use crate::cli::StrategyArgs;
+use crate::indicators;
+use crate::position::Position;
+use crate::sizing::{Sizer, SizingKind};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StrategyKind {
+    /// Long above MA * (1 + threshold), short below MA * (1 - threshold); see --ma-kind
+    Ma,
+    /// Long while a fast MA is above a slow MA, short while below (--fast-window, --slow-window, --ma-kind)
+    Crossover,
+    /// Buy below mean - k * std, sell short above mean + k * std over --ma-window, exit at the mean (--bb-k)
+    Bollinger,
+}
+
+impl StrategyKind {
+    /// Value of the `strategy` metric label
+    pub fn name(self) -> &'static str {
+        match self {
+            StrategyKind::Ma => "ma",
+            StrategyKind::Crossover => "crossover",
+            StrategyKind::Bollinger => "bollinger",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        }
+    }
+
+    /// +1 for buys, -1 for sells; signed position change is `sign() * qty`
+    pub fn sign(self) -> f64 {
+        match self {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        }
+    }
+}
+
+/// Parsed market trade as seen by strategies.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Trade {
+    /// Trade time `T`, ms since epoch
+    pub ts_ms: u64,
+    pub price: f64,
+    pub qty: f64,
+    pub is_buyer_maker: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderIntent {
+    pub side: Side,
+    pub qty: f64,
+    /// Price the decision was made at. Paper fills still go off the last
+    /// trade; the risk price band rejects entries where the two disagree.
+    pub price: f64,
+    /// Free-form context for the fill log, e.g. "ma=64000.12"
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fill {
+    pub side: Side,
+    pub qty: f64,
+    /// Execution price, slippage included
+    pub price: f64,
+    /// Fee paid in quote currency
+    pub fee: f64,
+}
+
+pub trait Strategy: Send {
+    /// Every trade, unless the engine is configured to aggregate bars
+    fn on_trade(&mut self, trade: &Trade) -> Vec<OrderIntent>;
+
+    /// Each completed bar, when running with `--bar`; replaces `on_trade`
+    fn on_bar(&mut self, _bar: &Bar) -> Vec<OrderIntent> {
+        Vec::new()
+    }
+
+    /// Periodic wakeup, driven by the engine's clock
+    fn on_timer(&mut self, _now_ms: u64) -> Vec<OrderIntent> {
+        Vec::new()
+    }
+
+    fn on_fill(&mut self, _fill: &Fill) {}
+
+    /// After --resume: the book's position and booked PnL, in place of the
+    /// fills that built them
+    fn on_restore(&mut self, _position: &Position) {}
+}
+
+/// Order that puts the position on side `dir` (-1 short, 0 flat, +1 long).
+/// Already being on that side is a no-op; a new side is sized by `sizer`,
+/// and a flip closes the old side in the same order. If the sizer returns 0
+/// (below min-notional) the position is only closed.
+fn to_side(
+    pos_qty: f64,
+    dir: f64,
+    sizer: &Sizer,
+    price: f64,
+    reason: impl FnOnce() -> String,
+) -> Vec<OrderIntent> {
+    let side_of = |x: f64| (x > 0.0) as i8 - (x < 0.0) as i8;
+    if side_of(pos_qty) == side_of(dir) {
+        return Vec::new();
+    }
+    let target = if dir == 0.0 { 0.0 } else { dir.signum() * sizer.qty(price) };
+    let delta = target - pos_qty;
+    if delta == 0.0 {
+        return Vec::new();
+    }
+    let side = if delta > 0.0 { Side::Buy } else { Side::Sell };
+    vec![OrderIntent { side, qty: delta.abs(), price, reason: reason() }]
+}
+
+/// Samples (trades, or bars with `--bar`) a fresh instance needs before its
+/// indicators and sizer are warm.
+pub fn warmup_samples(args: &StrategyArgs) -> usize {
+    let window = match args.strategy {
+        StrategyKind::Ma | StrategyKind::Bollinger => args.ma_window,
+        StrategyKind::Crossover => args.fast_window.max(args.slow_window),
+    };
+    match args.sizing.sizing {
+        // returns need one more price than the window holds
+        SizingKind::VolTarget => window.max(args.sizing.vol_window + 1),
+        _ => window,
+    }
+}
+
+/// Build a fresh instance for one symbol.
+pub fn build(args: &StrategyArgs, symbol: &str) -> Box<dyn Strategy> {
+    let sizer = Sizer::new(&args.sizing, symbol);
+    match args.strategy {
+        StrategyKind::Ma => {
+            let ma = indicators::build_ma(args.ma_kind, args.ma_window, args.half_life_secs);
+            Box::new(ma::MaThreshold::new(ma, args.threshold_bps, !args.long_only, sizer))
+        }
+        StrategyKind::Crossover => {
+            let fast = indicators::build_ma(args.ma_kind, args.fast_window, args.half_life_secs);
+            let slow = indicators::build_ma(args.ma_kind, args.slow_window, args.slow_half_life_secs);
+            Box::new(crossover::Crossover::new(fast, slow, !args.long_only, sizer))
+        }
+        StrategyKind::Bollinger => {
+            Box::new(bollinger::Bollinger::new(args.ma_window, args.bb_k, !args.long_only, sizer))
+        }
+    }
+}